@@ -1,6 +1,28 @@
+//! Raw zip structures as laid out in APPNOTE, for inspecting archives at a lower level than `Reader`.
+//!
+//! `to_bytes` and `from_bytes` convert without any check,
+//! `read_from` also checks the signature and reports where the structure is truncated.
+#![allow(clippy::zero_prefixed_literal)]
+
+use std::io::{
+    self,
+    ErrorKind,
+    Read,
+};
+
+use crate::error::ZipError;
+
+
 pub const TARGET_ZIP_VERSION: u16 = 45;
+/// Upper byte of "version made by" tells how to interpret external file attributes.
+pub const VERSION_MADE_BY_UNIX: u16 = 3 << 8;
 
 pub const GENERAL_PURPOSE_BIT_FLAG_DEFLATE_NORMAL: u16 = 0;
+pub const GENERAL_PURPOSE_BIT_FLAG_ENCRYPTED: u16 = 1 << 0;
+pub const GENERAL_PURPOSE_BIT_FLAG_DATA_DESCRIPTOR: u16 = 1 << 3;
+/// File name and comment are encoded in UTF-8 instead of code page 437.
+pub const GENERAL_PURPOSE_BIT_FLAG_UTF8: u16 = 1 << 11;
+pub const COMPRESSION_METHOD_STORED: u16 = 0;
 pub const COMPRESSION_METHOD_DEFLATE: u16 = 8;
 
 
@@ -36,6 +58,81 @@ impl LocalFileHeader {
         bytes[28..30].copy_from_slice(&self.extra_field_length.to_le_bytes());
         bytes
     }
+
+    pub fn from_bytes(bytes: &[u8; 30]) -> Self {
+        Self {
+            signature: bytes[00..04].try_into().unwrap(),
+            version_needed_to_extract: u16::from_le_bytes(bytes[04..06].try_into().unwrap()),
+            general_purpose_bit_flag: u16::from_le_bytes(bytes[06..08].try_into().unwrap()),
+            compression_method: u16::from_le_bytes(bytes[08..10].try_into().unwrap()),
+            last_modified_file_time: u16::from_le_bytes(bytes[10..12].try_into().unwrap()),
+            last_modified_file_date: u16::from_le_bytes(bytes[12..14].try_into().unwrap()),
+            crc_32: u32::from_le_bytes(bytes[14..18].try_into().unwrap()),
+            compressed_size: u32::from_le_bytes(bytes[18..22].try_into().unwrap()),
+            uncompressed_size: u32::from_le_bytes(bytes[22..26].try_into().unwrap()),
+            file_name_length: u16::from_le_bytes(bytes[26..28].try_into().unwrap()),
+            extra_field_length: u16::from_le_bytes(bytes[28..30].try_into().unwrap()),
+        }
+    }
+
+    /// Read the fixed size part at `offset` (only used in errors) of `r`, variable length fields follow it.
+    pub fn read_from(r: &mut impl Read, offset: u64) -> Result<Self, ZipError> {
+        let res = Self::from_bytes(&read_array(r).map_err(|err| truncated_at(err, offset))?);
+        check_signature(offset, Self::SIGNATURE, res.signature)?;
+        Ok(res)
+    }
+}
+
+/// Zip64 form of data descriptor, sizes are 8 bytes.
+#[repr(C)]
+pub struct DataDescriptor {
+    pub signature: [u8; 4],
+    pub crc_32: u32,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+}
+impl DataDescriptor {
+    pub const SIGNATURE: [u8; 4] = [b'P', b'K', 7, 8];
+
+    pub fn to_bytes(&self) -> [u8; 24] {
+        let mut bytes = [0u8; 24];
+        bytes[00..04].copy_from_slice(&self.signature);
+        bytes[04..08].copy_from_slice(&self.crc_32.to_le_bytes());
+        bytes[08..16].copy_from_slice(&self.compressed_size.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.uncompressed_size.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; 24]) -> Self {
+        Self {
+            signature: bytes[00..04].try_into().unwrap(),
+            crc_32: u32::from_le_bytes(bytes[04..08].try_into().unwrap()),
+            compressed_size: u64::from_le_bytes(bytes[08..16].try_into().unwrap()),
+            uncompressed_size: u64::from_le_bytes(bytes[16..24].try_into().unwrap()),
+        }
+    }
+
+    /// Read either form from `r`, `offset` is the position of `r` and only used in errors.
+    ///
+    /// The signature is optional, `signature` is left zeros if it is missing.
+    /// Sizes are 4 bytes unless `zip64`.
+    pub fn read_from(r: &mut impl Read, offset: u64, zip64: bool) -> Result<Self, ZipError> {
+        let mut read = || -> io::Result<Self> {
+            let mut signature = [0u8; 4];
+            let mut crc_32: [u8; 4] = read_array(r)?;
+            if crc_32 == Self::SIGNATURE {
+                signature = crc_32;
+                crc_32 = read_array(r)?;
+            }
+            let (compressed_size, uncompressed_size) = if zip64 {
+                (u64::from_le_bytes(read_array(r)?), u64::from_le_bytes(read_array(r)?))
+            } else {
+                (u32::from_le_bytes(read_array(r)?) as u64, u32::from_le_bytes(read_array(r)?) as u64)
+            };
+            Ok(Self { signature, crc_32: u32::from_le_bytes(crc_32), compressed_size, uncompressed_size })
+        };
+        read().map_err(|err| truncated_at(err, offset))
+    }
 }
 
 #[repr(C)]
@@ -55,14 +152,14 @@ pub struct CentralDirectoryHeader {
     pub file_comment_length: u16,
     pub disk_number_start: u16,
     pub internal_file_attributes: u16,
-    pub external_file_attributes: u16,
+    pub external_file_attributes: u32,
     pub relative_offset_of_local_header: u32,
 }
 impl CentralDirectoryHeader {
     pub const SIGNATURE: [u8; 4] = [b'P', b'K', 1, 2];
 
-    pub fn to_bytes(&self) -> [u8; 44] {
-        let mut bytes = [0u8; 44];
+    pub fn to_bytes(&self) -> [u8; 46] {
+        let mut bytes = [0u8; 46];
         bytes[00..04].copy_from_slice(&self.signature);
         bytes[04..06].copy_from_slice(&self.version_made_by.to_le_bytes());
         bytes[06..08].copy_from_slice(&self.version_needed_to_extract.to_le_bytes());
@@ -73,17 +170,47 @@ impl CentralDirectoryHeader {
         bytes[16..20].copy_from_slice(&self.crc_32.to_le_bytes());
         bytes[20..24].copy_from_slice(&self.compressed_size.to_le_bytes());
         bytes[24..28].copy_from_slice(&self.uncompressed_size.to_le_bytes());
-        bytes[28..30].copy_from_slice(&self.file_comment_length.to_le_bytes());
+        bytes[28..30].copy_from_slice(&self.file_name_length.to_le_bytes());
         bytes[30..32].copy_from_slice(&self.extra_field_length.to_le_bytes());
         bytes[32..34].copy_from_slice(&self.file_comment_length.to_le_bytes());
         bytes[34..36].copy_from_slice(&self.disk_number_start.to_le_bytes());
         bytes[36..38].copy_from_slice(&self.internal_file_attributes.to_le_bytes());
-        bytes[38..40].copy_from_slice(&self.external_file_attributes.to_le_bytes());
-        bytes[40..44].copy_from_slice(&self.relative_offset_of_local_header.to_le_bytes());
+        bytes[38..42].copy_from_slice(&self.external_file_attributes.to_le_bytes());
+        bytes[42..46].copy_from_slice(&self.relative_offset_of_local_header.to_le_bytes());
         bytes
     }
+
+    pub fn from_bytes(bytes: &[u8; 46]) -> Self {
+        Self {
+            signature: bytes[00..04].try_into().unwrap(),
+            version_made_by: u16::from_le_bytes(bytes[04..06].try_into().unwrap()),
+            version_needed_to_extract: u16::from_le_bytes(bytes[06..08].try_into().unwrap()),
+            general_purpose_bit_flag: u16::from_le_bytes(bytes[08..10].try_into().unwrap()),
+            compression_method: u16::from_le_bytes(bytes[10..12].try_into().unwrap()),
+            last_modified_file_time: u16::from_le_bytes(bytes[12..14].try_into().unwrap()),
+            last_modified_file_date: u16::from_le_bytes(bytes[14..16].try_into().unwrap()),
+            crc_32: u32::from_le_bytes(bytes[16..20].try_into().unwrap()),
+            compressed_size: u32::from_le_bytes(bytes[20..24].try_into().unwrap()),
+            uncompressed_size: u32::from_le_bytes(bytes[24..28].try_into().unwrap()),
+            file_name_length: u16::from_le_bytes(bytes[28..30].try_into().unwrap()),
+            extra_field_length: u16::from_le_bytes(bytes[30..32].try_into().unwrap()),
+            file_comment_length: u16::from_le_bytes(bytes[32..34].try_into().unwrap()),
+            disk_number_start: u16::from_le_bytes(bytes[34..36].try_into().unwrap()),
+            internal_file_attributes: u16::from_le_bytes(bytes[36..38].try_into().unwrap()),
+            external_file_attributes: u32::from_le_bytes(bytes[38..42].try_into().unwrap()),
+            relative_offset_of_local_header: u32::from_le_bytes(bytes[42..46].try_into().unwrap()),
+        }
+    }
+
+    /// Read the fixed size part at `offset` (only used in errors) of `r`, variable length fields follow it.
+    pub fn read_from(r: &mut impl Read, offset: u64) -> Result<Self, ZipError> {
+        let res = Self::from_bytes(&read_array(r).map_err(|err| truncated_at(err, offset))?);
+        check_signature(offset, Self::SIGNATURE, res.signature)?;
+        Ok(res)
+    }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
 #[repr(C)]
 pub struct Zip64ExtraField {
     pub header_id: u16,
@@ -120,9 +247,48 @@ impl Zip64ExtraField {
         bytes[2..4].copy_from_slice(&size.to_le_bytes());
         bytes
     }
+
+    /// Parse a zip64 extra field (header included) which contains only the `selected` values,
+    /// values not selected are left 0. Returns `None` if the header id is not zip64,
+    /// or `data_size` is not the size of the `selected` values, or the field is too short.
+    pub fn select_from_bytes(bytes: &[u8], selected: &[Zip64ExtraFieldSelect]) -> Option<Self> {
+        let mut res = Self {
+            header_id: u16::from_le_bytes(bytes.get(0..2)?.try_into().unwrap()),
+            data_size: u16::from_le_bytes(bytes.get(2..4)?.try_into().unwrap()),
+            uncompressed_size: 0,
+            compressed_size: 0,
+            relative_offset_of_local_header: 0,
+            disk_number_start: 0,
+        };
+        let selected_size = selected.iter()
+            .map(|select| if *select == Zip64ExtraFieldSelect::DiskNumberStart { 4 } else { 8 })
+            .sum::<u16>();
+        if res.header_id != Self::HEADER_ID || res.data_size != selected_size {
+            return None;
+        }
+        let mut pos = 4;
+        let mut next_u64 = || {
+            let value = u64::from_le_bytes(bytes.get(pos..pos + 8)?.try_into().unwrap());
+            pos += 8;
+            Some(value)
+        };
+        if selected.contains(&Zip64ExtraFieldSelect::UncompressedSize) {
+            res.uncompressed_size = next_u64()?;
+        }
+        if selected.contains(&Zip64ExtraFieldSelect::CompressedSize) {
+            res.compressed_size = next_u64()?;
+        }
+        if selected.contains(&Zip64ExtraFieldSelect::RelativeOffsetOfLocalHeader) {
+            res.relative_offset_of_local_header = next_u64()?;
+        }
+        if selected.contains(&Zip64ExtraFieldSelect::DiskNumberStart) {
+            res.disk_number_start = u32::from_le_bytes(bytes.get(pos..pos + 4)?.try_into().unwrap());
+        }
+        Some(res)
+    }
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Zip64ExtraFieldSelect {
     UncompressedSize,
     CompressedSize,
@@ -130,6 +296,373 @@ pub enum Zip64ExtraFieldSelect {
     DiskNumberStart,
 }
 
+/// Info-ZIP extended timestamp, times are signed seconds since unix epoch in UTC.
+///
+/// The central directory version only contains the modification time,
+/// but its flags still tell which times are in the local version.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct ExtendedTimestampExtraField {
+    pub header_id: u16,
+    pub data_size: u16,
+    pub flags: u8,
+    pub modification_time: i32,
+    pub access_time: i32,
+    pub creation_time: i32,
+}
+impl ExtendedTimestampExtraField {
+    pub const HEADER_ID: u16 = 0x5455;
+    pub const FLAG_MODIFICATION_TIME: u8 = 1 << 0;
+    pub const FLAG_ACCESS_TIME: u8 = 1 << 1;
+    pub const FLAG_CREATION_TIME: u8 = 1 << 2;
+
+    /// `central` selects the central directory version, `data_size` is set automatically.
+    pub fn to_bytes(&self, central: bool) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(17);
+        bytes.extend_from_slice(&self.header_id.to_le_bytes());
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes.push(self.flags);
+        if self.flags & Self::FLAG_MODIFICATION_TIME != 0 {
+            bytes.extend_from_slice(&self.modification_time.to_le_bytes());
+        }
+        if !central {
+            if self.flags & Self::FLAG_ACCESS_TIME != 0 {
+                bytes.extend_from_slice(&self.access_time.to_le_bytes());
+            }
+            if self.flags & Self::FLAG_CREATION_TIME != 0 {
+                bytes.extend_from_slice(&self.creation_time.to_le_bytes());
+            }
+        }
+        let size = (bytes.len() - 4) as u16;
+        bytes[2..4].copy_from_slice(&size.to_le_bytes());
+        bytes
+    }
+
+    /// Parse either version (header included), times flagged but missing from `bytes` are left 0.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut res = Self {
+            header_id: u16::from_le_bytes(bytes.get(0..2)?.try_into().unwrap()),
+            data_size: u16::from_le_bytes(bytes.get(2..4)?.try_into().unwrap()),
+            flags: *bytes.get(4)?,
+            modification_time: 0,
+            access_time: 0,
+            creation_time: 0,
+        };
+        let mut times = bytes[5..].chunks_exact(4).map(|b| i32::from_le_bytes(b.try_into().unwrap()));
+        if res.flags & Self::FLAG_MODIFICATION_TIME != 0 {
+            res.modification_time = times.next().unwrap_or(0);
+        }
+        if res.flags & Self::FLAG_ACCESS_TIME != 0 {
+            res.access_time = times.next().unwrap_or(0);
+        }
+        if res.flags & Self::FLAG_CREATION_TIME != 0 {
+            res.creation_time = times.next().unwrap_or(0);
+        }
+        Some(res)
+    }
+}
+
+/// NTFS extra field with only the timestamps attribute (tag 1),
+/// times are in 100 nanoseconds since 1601-01-01 UTC (Windows `FILETIME`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct NtfsExtraField {
+    pub header_id: u16,
+    pub data_size: u16,
+    pub reserved: u32,
+    pub modification_time: u64,
+    pub access_time: u64,
+    pub creation_time: u64,
+}
+impl NtfsExtraField {
+    pub const HEADER_ID: u16 = 0x000a;
+    pub const TAG_TIMESTAMPS: u16 = 1;
+
+    /// `data_size` is ignored, only the timestamps attribute is written.
+    pub fn to_bytes(&self) -> [u8; 36] {
+        let mut bytes = [0u8; 36];
+        bytes[00..02].copy_from_slice(&self.header_id.to_le_bytes());
+        bytes[02..04].copy_from_slice(&32u16.to_le_bytes());
+        bytes[04..08].copy_from_slice(&self.reserved.to_le_bytes());
+        bytes[08..10].copy_from_slice(&Self::TAG_TIMESTAMPS.to_le_bytes());
+        bytes[10..12].copy_from_slice(&24u16.to_le_bytes());
+        bytes[12..20].copy_from_slice(&self.modification_time.to_le_bytes());
+        bytes[20..28].copy_from_slice(&self.access_time.to_le_bytes());
+        bytes[28..36].copy_from_slice(&self.creation_time.to_le_bytes());
+        bytes
+    }
+
+    /// Parse the field (header included), `None` if there is no timestamps attribute.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut rest = bytes.get(8..)?;
+        while rest.len() >= 4 {
+            let tag = u16::from_le_bytes([rest[0], rest[1]]);
+            let size = u16::from_le_bytes([rest[2], rest[3]]) as usize;
+            let attribute = rest.get(4..4 + size)?;
+            if tag == Self::TAG_TIMESTAMPS && size >= 24 {
+                return Some(Self {
+                    header_id: u16::from_le_bytes(bytes[0..2].try_into().unwrap()),
+                    data_size: u16::from_le_bytes(bytes[2..4].try_into().unwrap()),
+                    reserved: u32::from_le_bytes(bytes[4..8].try_into().unwrap()),
+                    modification_time: u64::from_le_bytes(attribute[00..08].try_into().unwrap()),
+                    access_time: u64::from_le_bytes(attribute[08..16].try_into().unwrap()),
+                    creation_time: u64::from_le_bytes(attribute[16..24].try_into().unwrap()),
+                });
+            }
+            rest = &rest[4 + size..];
+        }
+        None
+    }
+}
+
+/// Info-ZIP new Unix extra field ("ux"), holding uid and gid of the entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct UnixExtraField {
+    pub header_id: u16,
+    pub data_size: u16,
+    pub version: u8,
+    pub uid: u32,
+    pub gid: u32,
+}
+impl UnixExtraField {
+    pub const HEADER_ID: u16 = 0x7875;
+    pub const VERSION: u8 = 1;
+
+    /// uid and gid are always written in 4 bytes, `data_size` is ignored.
+    pub fn to_bytes(&self) -> [u8; 15] {
+        let mut bytes = [0u8; 15];
+        bytes[00..02].copy_from_slice(&self.header_id.to_le_bytes());
+        bytes[02..04].copy_from_slice(&11u16.to_le_bytes());
+        bytes[04] = self.version;
+        bytes[05] = 4;
+        bytes[06..10].copy_from_slice(&self.uid.to_le_bytes());
+        bytes[10] = 4;
+        bytes[11..15].copy_from_slice(&self.gid.to_le_bytes());
+        bytes
+    }
+
+    /// Parse the field (header included), `None` if it is truncated, of unknown version,
+    /// or uid or gid does not fit in `u32`.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        fn read_id(bytes: &[u8]) -> Option<(u32, &[u8])> {
+            let size = *bytes.first()? as usize;
+            let id = bytes.get(1..1 + size)?;
+            if id.iter().skip(4).any(|&b| b != 0) {
+                return None;
+            }
+            let mut le = [0u8; 4];
+            let n = size.min(4);
+            le[..n].copy_from_slice(&id[..n]);
+            Some((u32::from_le_bytes(le), &bytes[1 + size..]))
+        }
+        let version = *bytes.get(4)?;
+        if version != Self::VERSION {
+            return None;
+        }
+        let (uid, rest) = read_id(&bytes[5..])?;
+        let (gid, _) = read_id(rest)?;
+        Some(Self {
+            header_id: u16::from_le_bytes(bytes[0..2].try_into().unwrap()),
+            data_size: u16::from_le_bytes(bytes[2..4].try_into().unwrap()),
+            version,
+            uid,
+            gid,
+        })
+    }
+}
+
+/// Info-ZIP Unicode path extra field, the UTF-8 version of a file name that is not UTF-8 flagged.
+///
+/// It is only valid if `name_crc_32` matches the crc 32 of the file name in the header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct UnicodePathExtraField {
+    pub header_id: u16,
+    pub data_size: u16,
+    pub version: u8,
+    pub name_crc_32: u32,
+    pub unicode_name: Vec<u8>,
+}
+impl UnicodePathExtraField {
+    pub const HEADER_ID: u16 = 0x7075;
+    pub const VERSION: u8 = 1;
+
+    /// `data_size` is set automatically.
+    ///
+    /// Panics if `unicode_name` is longer than `u16::MAX - 5` bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let data_size = u16::try_from(5 + self.unicode_name.len()).expect("unicode name too long for an extra field");
+        let mut bytes = Vec::with_capacity(9 + self.unicode_name.len());
+        bytes.extend_from_slice(&self.header_id.to_le_bytes());
+        bytes.extend_from_slice(&data_size.to_le_bytes());
+        bytes.push(self.version);
+        bytes.extend_from_slice(&self.name_crc_32.to_le_bytes());
+        bytes.extend_from_slice(&self.unicode_name);
+        bytes
+    }
+
+    /// Parse the field (header included).
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(Self {
+            header_id: u16::from_le_bytes(bytes.get(0..2)?.try_into().unwrap()),
+            data_size: u16::from_le_bytes(bytes.get(2..4)?.try_into().unwrap()),
+            version: *bytes.get(4)?,
+            name_crc_32: u32::from_le_bytes(bytes.get(5..9)?.try_into().unwrap()),
+            unicode_name: bytes[9..].to_vec(),
+        })
+    }
+}
+
+/// Info-ZIP Unicode comment extra field, the UTF-8 version of a file comment that is not UTF-8 flagged.
+///
+/// It is only valid if `comment_crc_32` matches the crc 32 of the file comment in the central directory header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct UnicodeCommentExtraField {
+    pub header_id: u16,
+    pub data_size: u16,
+    pub version: u8,
+    pub comment_crc_32: u32,
+    pub unicode_comment: Vec<u8>,
+}
+impl UnicodeCommentExtraField {
+    pub const HEADER_ID: u16 = 0x6375;
+    pub const VERSION: u8 = 1;
+
+    /// `data_size` is set automatically.
+    ///
+    /// Panics if `unicode_comment` is longer than `u16::MAX - 5` bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let data_size = u16::try_from(5 + self.unicode_comment.len()).expect("unicode comment too long for an extra field");
+        let mut bytes = Vec::with_capacity(9 + self.unicode_comment.len());
+        bytes.extend_from_slice(&self.header_id.to_le_bytes());
+        bytes.extend_from_slice(&data_size.to_le_bytes());
+        bytes.push(self.version);
+        bytes.extend_from_slice(&self.comment_crc_32.to_le_bytes());
+        bytes.extend_from_slice(&self.unicode_comment);
+        bytes
+    }
+
+    /// Parse the field (header included).
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(Self {
+            header_id: u16::from_le_bytes(bytes.get(0..2)?.try_into().unwrap()),
+            data_size: u16::from_le_bytes(bytes.get(2..4)?.try_into().unwrap()),
+            version: *bytes.get(4)?,
+            comment_crc_32: u32::from_le_bytes(bytes.get(5..9)?.try_into().unwrap()),
+            unicode_comment: bytes[9..].to_vec(),
+        })
+    }
+}
+
+/// An extra field of any kind, known kinds are parsed and the others are kept as bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExtraField {
+    Zip64(Zip64ExtraField),
+    ExtendedTimestamp(ExtendedTimestampExtraField),
+    Ntfs(NtfsExtraField),
+    Unix(UnixExtraField),
+    UnicodePath(UnicodePathExtraField),
+    UnicodeComment(UnicodeCommentExtraField),
+    /// Unknown kind, or a known kind that fails to parse, `data` excludes header id and data size.
+    Unknown { id: u16, data: Vec<u8> },
+}
+impl ExtraField {
+    pub fn header_id(&self) -> u16 {
+        match self {
+            Self::Zip64(field) => field.header_id,
+            Self::ExtendedTimestamp(field) => field.header_id,
+            Self::Ntfs(field) => field.header_id,
+            Self::Unix(field) => field.header_id,
+            Self::UnicodePath(field) => field.header_id,
+            Self::UnicodeComment(field) => field.header_id,
+            Self::Unknown { id, .. } => *id,
+        }
+    }
+
+    /// Parse one field (header included), `None` if `bytes` is shorter than the header.
+    ///
+    /// Values of a zip64 extra field are taken in order as far as `data_size` goes,
+    /// since which of them are present depends on the header it belongs to.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let id = u16::from_le_bytes(bytes.get(0..2)?.try_into().unwrap());
+        let data = bytes.get(4..)?;
+        let parsed = match id {
+            Zip64ExtraField::HEADER_ID =>
+                Zip64ExtraField::select_from_bytes(bytes, zip64_selected_by_size(data.len())).map(Self::Zip64),
+            ExtendedTimestampExtraField::HEADER_ID => ExtendedTimestampExtraField::from_bytes(bytes).map(Self::ExtendedTimestamp),
+            NtfsExtraField::HEADER_ID => NtfsExtraField::from_bytes(bytes).map(Self::Ntfs),
+            UnixExtraField::HEADER_ID => UnixExtraField::from_bytes(bytes).map(Self::Unix),
+            UnicodePathExtraField::HEADER_ID => UnicodePathExtraField::from_bytes(bytes).map(Self::UnicodePath),
+            UnicodeCommentExtraField::HEADER_ID => UnicodeCommentExtraField::from_bytes(bytes).map(Self::UnicodeComment),
+            _ => None,
+        };
+        Some(parsed.unwrap_or_else(|| Self::Unknown { id, data: data.to_vec() }))
+    }
+
+    /// Parse the extra field block of a header, a truncated field at the end is dropped.
+    pub fn parse_all(extra_fields: &[u8]) -> Vec<Self> {
+        let mut res = Vec::new();
+        let mut rest = extra_fields;
+        while rest.len() >= 4 {
+            let size = u16::from_le_bytes([rest[2], rest[3]]) as usize;
+            let Some(field) = rest.get(..4 + size) else { break };
+            res.extend(Self::from_bytes(field));
+            rest = &rest[4 + size..];
+        }
+        res
+    }
+
+    /// `central` selects the central directory version of extended timestamp extra field,
+    /// zip64 extra field is written with values as far as its `data_size` goes.
+    pub fn to_bytes(&self, central: bool) -> Vec<u8> {
+        match self {
+            Self::Zip64(field) => field.select_to_bytes(zip64_selected_by_size(field.data_size as usize)),
+            Self::ExtendedTimestamp(field) => field.to_bytes(central),
+            Self::Ntfs(field) => field.to_bytes().to_vec(),
+            Self::Unix(field) => field.to_bytes().to_vec(),
+            Self::UnicodePath(field) => field.to_bytes(),
+            Self::UnicodeComment(field) => field.to_bytes(),
+            Self::Unknown { id, data } => {
+                let mut bytes = Vec::with_capacity(4 + data.len());
+                bytes.extend_from_slice(&id.to_le_bytes());
+                bytes.extend_from_slice(&(data.len() as u16).to_le_bytes());
+                bytes.extend_from_slice(data);
+                bytes
+            },
+        }
+    }
+}
+
+/// Values of a zip64 extra field in order, as far as `data_size` goes.
+fn zip64_selected_by_size(data_size: usize) -> &'static [Zip64ExtraFieldSelect] {
+    const ALL: [Zip64ExtraFieldSelect; 4] = [
+        Zip64ExtraFieldSelect::UncompressedSize,
+        Zip64ExtraFieldSelect::CompressedSize,
+        Zip64ExtraFieldSelect::RelativeOffsetOfLocalHeader,
+        Zip64ExtraFieldSelect::DiskNumberStart,
+    ];
+    let count = if data_size >= 28 { 4 } else { (data_size / 8).min(3) };
+    &ALL[..count]
+}
+
+/// Find the extra field with `header_id` in the extra field block of a header,
+/// the returned slice includes the 4 bytes of header id and data size.
+pub fn find_extra_field(extra_fields: &[u8], header_id: u16) -> Option<&[u8]> {
+    let mut rest = extra_fields;
+    while rest.len() >= 4 {
+        let id = u16::from_le_bytes([rest[0], rest[1]]);
+        let size = u16::from_le_bytes([rest[2], rest[3]]) as usize;
+        let field = rest.get(..4 + size)?;
+        if id == header_id {
+            return Some(field);
+        }
+        rest = &rest[4 + size..];
+    }
+    None
+}
+
 #[repr(C)]
 pub struct Zip64EndOfCentralDirectoryRecord {
     pub signature: [u8; 4],
@@ -161,12 +694,34 @@ impl Zip64EndOfCentralDirectoryRecord {
         bytes[48..56].copy_from_slice(&self.offset_of_start_of_central_directory_with_respect_to_the_starting_disk_number.to_le_bytes());
         bytes
     }
+
+    pub fn from_bytes(bytes: &[u8; 56]) -> Self {
+        Self {
+            signature: bytes[00..04].try_into().unwrap(),
+            size_of_zip64_end_of_central_directory_record: u64::from_le_bytes(bytes[04..12].try_into().unwrap()),
+            version_made_by: u16::from_le_bytes(bytes[12..14].try_into().unwrap()),
+            version_needed_to_extract: u16::from_le_bytes(bytes[14..16].try_into().unwrap()),
+            number_of_this_disk: u32::from_le_bytes(bytes[16..20].try_into().unwrap()),
+            number_of_the_disk_with_the_start_of_the_central_directory: u32::from_le_bytes(bytes[20..24].try_into().unwrap()),
+            total_number_of_entries_in_the_central_directory_on_this_disk: u64::from_le_bytes(bytes[24..32].try_into().unwrap()),
+            total_number_of_entries_in_the_central_directory: u64::from_le_bytes(bytes[32..40].try_into().unwrap()),
+            size_of_the_central_directory: u64::from_le_bytes(bytes[40..48].try_into().unwrap()),
+            offset_of_start_of_central_directory_with_respect_to_the_starting_disk_number: u64::from_le_bytes(bytes[48..56].try_into().unwrap()),
+        }
+    }
+
+    /// Read from `r`, `offset` is the position of `r` and only used in errors.
+    pub fn read_from(r: &mut impl Read, offset: u64) -> Result<Self, ZipError> {
+        let res = Self::from_bytes(&read_array(r).map_err(|err| truncated_at(err, offset))?);
+        check_signature(offset, Self::SIGNATURE, res.signature)?;
+        Ok(res)
+    }
 }
 
 #[repr(C)]
 pub struct Zip64EndOfCentralDirectoryLocator {
     pub signature: [u8; 4],
-    pub number_of_the_disk_with_the_start_of_the_zip64_end_of_central_irectory: u32,
+    pub number_of_the_disk_with_the_start_of_the_zip64_end_of_central_directory: u32,
     pub relative_offset_of_the_zip64_end_of_central_directory_record: u64,
     pub total_number_of_disks: u32,
 }
@@ -176,11 +731,27 @@ impl Zip64EndOfCentralDirectoryLocator {
     pub fn to_bytes(&self) -> [u8; 20] {
         let mut bytes = [0u8; 20];
         bytes[00..04].copy_from_slice(&self.signature);
-        bytes[04..08].copy_from_slice(&self.number_of_the_disk_with_the_start_of_the_zip64_end_of_central_irectory.to_le_bytes());
+        bytes[04..08].copy_from_slice(&self.number_of_the_disk_with_the_start_of_the_zip64_end_of_central_directory.to_le_bytes());
         bytes[08..16].copy_from_slice(&self.relative_offset_of_the_zip64_end_of_central_directory_record.to_le_bytes());
         bytes[16..20].copy_from_slice(&self.total_number_of_disks.to_le_bytes());
         bytes
     }
+
+    pub fn from_bytes(bytes: &[u8; 20]) -> Self {
+        Self {
+            signature: bytes[00..04].try_into().unwrap(),
+            number_of_the_disk_with_the_start_of_the_zip64_end_of_central_directory: u32::from_le_bytes(bytes[04..08].try_into().unwrap()),
+            relative_offset_of_the_zip64_end_of_central_directory_record: u64::from_le_bytes(bytes[08..16].try_into().unwrap()),
+            total_number_of_disks: u32::from_le_bytes(bytes[16..20].try_into().unwrap()),
+        }
+    }
+
+    /// Read from `r`, `offset` is the position of `r` and only used in errors.
+    pub fn read_from(r: &mut impl Read, offset: u64) -> Result<Self, ZipError> {
+        let res = Self::from_bytes(&read_array(r).map_err(|err| truncated_at(err, offset))?);
+        check_signature(offset, Self::SIGNATURE, res.signature)?;
+        Ok(res)
+    }
 }
 
 #[repr(C)]
@@ -209,4 +780,46 @@ impl EndOfCentralDirectoryRecord {
         bytes[20..22].copy_from_slice(&self.zip_file_comment_length.to_le_bytes());
         bytes
     }
+
+    pub fn from_bytes(bytes: &[u8; 22]) -> Self {
+        Self {
+            signature: bytes[00..04].try_into().unwrap(),
+            number_of_this_disk: u16::from_le_bytes(bytes[04..06].try_into().unwrap()),
+            number_of_the_disk_with_the_start_of_the_central_directory: u16::from_le_bytes(bytes[06..08].try_into().unwrap()),
+            total_number_of_entries_in_the_central_directory_on_this_disk: u16::from_le_bytes(bytes[08..10].try_into().unwrap()),
+            total_number_of_entries_in_the_central_directory: u16::from_le_bytes(bytes[10..12].try_into().unwrap()),
+            size_of_the_central_directory: u32::from_le_bytes(bytes[12..16].try_into().unwrap()),
+            offset_of_start_of_central_directory_with_respect_to_the_starting_disk_number: u32::from_le_bytes(bytes[16..20].try_into().unwrap()),
+            zip_file_comment_length: u16::from_le_bytes(bytes[20..22].try_into().unwrap()),
+        }
+    }
+
+    /// Read from `r`, `offset` is the position of `r` and only used in errors.
+    pub fn read_from(r: &mut impl Read, offset: u64) -> Result<Self, ZipError> {
+        let res = Self::from_bytes(&read_array(r).map_err(|err| truncated_at(err, offset))?);
+        check_signature(offset, Self::SIGNATURE, res.signature)?;
+        Ok(res)
+    }
+}
+
+pub(crate) fn read_array<const N: usize>(r: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+pub(crate) fn truncated_at(err: io::Error, offset: u64) -> ZipError {
+    if err.kind() == ErrorKind::UnexpectedEof {
+        ZipError::Truncated { offset }
+    } else {
+        ZipError::Io(err)
+    }
+}
+
+pub(crate) fn check_signature(offset: u64, expected: [u8; 4], found: [u8; 4]) -> Result<(), ZipError> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(ZipError::InvalidSignature { offset, expected, found })
+    }
 }
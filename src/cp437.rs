@@ -0,0 +1,29 @@
+/// Characters of code page 437 from `0x80` to `0xFF`, the lower half is taken as ASCII.
+const UPPER_HALF: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{A0}',
+];
+
+
+pub fn decode(bytes: &[u8]) -> String {
+    bytes.iter()
+        .map(|&b| if b < 0x80 { b as char } else { UPPER_HALF[b as usize - 0x80] })
+        .collect()
+}
+
+/// Characters not in code page 437 are replaced by `_`.
+pub fn encode(s: &str) -> Vec<u8> {
+    s.chars()
+        .map(|c| if c.is_ascii() {
+            c as u8
+        } else {
+            UPPER_HALF.iter().position(|&u| u == c).map_or(b'_', |i| i as u8 + 0x80)
+        })
+        .collect()
+}
@@ -47,10 +47,255 @@ pub const LOOKUP_TABLE: [u32; 256] = [
 //}
 
 
+/// `SLICE_TABLES[k][i]` is the crc of byte `i` followed by `k` zero bytes, `SLICE_TABLES[0]` is `LOOKUP_TABLE`.
+const SLICE_TABLES: [[u32; 256]; 16] = {
+    let mut tables = [LOOKUP_TABLE; 16];
+    let mut k = 1;
+    while k < 16 {
+        let mut i = 0;
+        while i < 256 {
+            let prev = tables[k - 1][i];
+            tables[k][i] = (prev >> 8) ^ LOOKUP_TABLE[(prev & 0xFF) as usize];
+            i += 1;
+        }
+        k += 1;
+    }
+    tables
+};
+
+
+/// Crc 32 as used in zip (reflected polynomial `0xEDB88320`), also usable as a `Hasher`.
+///
+/// ```
+/// use singletonzip_rs::Crc32;
+///
+/// let mut crc = Crc32::new();
+/// crc.update(b"1234");
+/// crc.update(b"56789");
+/// assert!(crc.finalize() == 0xcbf43926);
+/// assert!(Crc32::combine(Crc32::checksum(b"1234"), Crc32::checksum(b"56789"), 5) == 0xcbf43926);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Crc32 {
+    crc: u32,
+}
+impl Crc32 {
+    pub fn new() -> Self {
+        Self { crc: 0 }
+    }
+
+    /// Continue from a crc of some previous bytes.
+    pub fn from_crc(crc: u32) -> Self {
+        Self { crc }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.crc = run(self.crc, bytes);
+    }
+
+    pub fn finalize(self) -> u32 {
+        self.crc
+    }
+
+    pub fn checksum(bytes: &[u8]) -> u32 {
+        run(0, bytes)
+    }
+
+    /// Crc of `a` followed by `b`, given `crc_a` of `a`, `crc_b` of `b` and the length of `b`.
+    ///
+    /// It takes `O(log len_b)` time, so chunks can be checksummed in parallel and combined afterwards.
+    pub fn combine(crc_a: u32, crc_b: u32, len_b: u64) -> u32 {
+        // shifting `crc_a` by `len_b` zero bytes is a multiplication by x^(8 * len_b) modulo the polynomial
+        mul_mod_poly(x_pow_8n_mod_poly(len_b), crc_a) ^ crc_b
+    }
+}
+impl std::hash::Hasher for Crc32 {
+    fn write(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.crc as u64
+    }
+}
+
+const POLYNOMIAL: u32 = 0xEDB88320;
+
+/// `a * b` modulo the polynomial, in reflected bit order where `1 << 31` is x^0. `a` is not 0.
+const fn mul_mod_poly(a: u32, mut b: u32) -> u32 {
+    let mut m = 1 << 31;
+    let mut res = 0;
+    loop {
+        if a & m != 0 {
+            res ^= b;
+            if a & (m - 1) == 0 {
+                return res;
+            }
+        }
+        m >>= 1;
+        b = if b & 1 != 0 { (b >> 1) ^ POLYNOMIAL } else { b >> 1 };
+    }
+}
+
+/// `X_POW_2K[k]` is x^(2^k) modulo the polynomial.
+const X_POW_2K: [u32; 32] = {
+    let mut table = [0; 32];
+    let mut p = 1 << 30; // x^1
+    let mut k = 0;
+    while k < 32 {
+        table[k] = p;
+        p = mul_mod_poly(p, p);
+        k += 1;
+    }
+    table
+};
+
+/// x^(8 * n) modulo the polynomial.
+fn x_pow_8n_mod_poly(mut n: u64) -> u32 {
+    let mut res = 1 << 31; // x^0
+    let mut k = 3; // 8 = 2^3
+    while n != 0 {
+        if n & 1 != 0 {
+            res = mul_mod_poly(X_POW_2K[k % 32], res);
+        }
+        n >>= 1;
+        k += 1;
+    }
+    res
+}
+
+
+/// Update `crc` with `bytes`, using the fastest implementation the cpu supports.
 pub fn run(crc: u32, bytes: &[u8]) -> u32 {
+    #[cfg(target_arch = "x86_64")]
+    if bytes.len() >= 128 && is_x86_feature_detected!("pclmulqdq") && is_x86_feature_detected!("sse4.1") {
+        // SAFETY: required cpu features are detected above
+        return unsafe { pclmulqdq::run(crc, bytes) };
+    }
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("crc") {
+        // SAFETY: required cpu features are detected above
+        return unsafe { aarch64::run(crc, bytes) };
+    }
+    run_slice_by_16(crc, bytes)
+}
+
+/// Portable version processing 16 bytes per step.
+pub fn run_slice_by_16(crc: u32, bytes: &[u8]) -> u32 {
+    let mut tmp = !crc;
+    let mut chunks = bytes.chunks_exact(16);
+    for chunk in &mut chunks {
+        let low = tmp ^ u32::from_le_bytes(chunk[0..4].try_into().unwrap());
+        let mut res = 0;
+        for (i, &b) in low.to_le_bytes().iter().chain(&chunk[4..16]).enumerate() {
+            res ^= SLICE_TABLES[15 - i][b as usize];
+        }
+        tmp = res;
+    }
+    run_bytewise(!tmp, chunks.remainder())
+}
+
+/// Reference version processing 1 byte per step.
+pub fn run_bytewise(crc: u32, bytes: &[u8]) -> u32 {
     let mut tmp = !crc;
     for b in bytes {
         tmp = (tmp >> 8) ^ LOOKUP_TABLE[(b ^ tmp as u8) as usize];
     }
     !tmp
 }
+
+
+/// Folding with carry-less multiplication, see Intel's "Fast CRC Computation for Generic Polynomials
+/// Using PCLMULQDQ Instruction", constants are for the bit reflected crc 32 polynomial.
+#[cfg(target_arch = "x86_64")]
+mod pclmulqdq {
+    use std::arch::x86_64::*;
+
+    const K1: i64 = 0x1_5444_2bd4;
+    const K2: i64 = 0x1_c6e4_1596;
+    const K3: i64 = 0x1_7519_97d0;
+    const K4: i64 = 0x0_ccaa_009e;
+    const K5: i64 = 0x1_63cd_6124;
+    const P_X: i64 = 0x1_db71_0641;
+    const U_PRIME: i64 = 0x1_f701_1641;
+
+    /// `bytes` is at least 128 bytes long.
+    #[target_feature(enable = "pclmulqdq,sse4.1")]
+    pub fn run(crc: u32, mut bytes: &[u8]) -> u32 {
+        debug_assert!(bytes.len() >= 128);
+        // fold by 4
+        let mut x3 = load(&mut bytes);
+        let mut x2 = load(&mut bytes);
+        let mut x1 = load(&mut bytes);
+        let mut x0 = load(&mut bytes);
+        x3 = _mm_xor_si128(x3, _mm_cvtsi32_si128(!crc as i32));
+        let k1k2 = _mm_set_epi64x(K2, K1);
+        while bytes.len() >= 64 {
+            x3 = fold(x3, load(&mut bytes), k1k2);
+            x2 = fold(x2, load(&mut bytes), k1k2);
+            x1 = fold(x1, load(&mut bytes), k1k2);
+            x0 = fold(x0, load(&mut bytes), k1k2);
+        }
+        let k3k4 = _mm_set_epi64x(K4, K3);
+        let mut x = fold(x3, x2, k3k4);
+        x = fold(x, x1, k3k4);
+        x = fold(x, x0, k3k4);
+        // fold by 1
+        while bytes.len() >= 16 {
+            x = fold(x, load(&mut bytes), k3k4);
+        }
+
+        // reduce 128 bits to 64 bits
+        let low_32 = _mm_set_epi32(0, 0, 0, !0);
+        let x = _mm_xor_si128(_mm_clmulepi64_si128(x, k3k4, 0x10), _mm_srli_si128(x, 8));
+        let x = _mm_xor_si128(
+            _mm_clmulepi64_si128(_mm_and_si128(x, low_32), _mm_set_epi64x(0, K5), 0x00),
+            _mm_srli_si128(x, 4),
+        );
+        // Barrett reduction from 64 bits to 32 bits
+        let pu = _mm_set_epi64x(U_PRIME, P_X);
+        let t1 = _mm_clmulepi64_si128(_mm_and_si128(x, low_32), pu, 0x10);
+        let t2 = _mm_clmulepi64_si128(_mm_and_si128(t1, low_32), pu, 0x00);
+        let crc = !(_mm_extract_epi32(_mm_xor_si128(x, t2), 1) as u32);
+
+        super::run_slice_by_16(crc, bytes)
+    }
+
+    #[target_feature(enable = "pclmulqdq,sse4.1")]
+    fn fold(a: __m128i, b: __m128i, keys: __m128i) -> __m128i {
+        let t1 = _mm_clmulepi64_si128(a, keys, 0x00);
+        let t2 = _mm_clmulepi64_si128(a, keys, 0x11);
+        _mm_xor_si128(_mm_xor_si128(b, t1), t2)
+    }
+
+    #[target_feature(enable = "sse2")]
+    fn load(bytes: &mut &[u8]) -> __m128i {
+        assert!(bytes.len() >= 16);
+        // SAFETY: 16 bytes are readable
+        let res = unsafe { _mm_loadu_si128(bytes.as_ptr() as *const __m128i) };
+        *bytes = &bytes[16..];
+        res
+    }
+}
+
+/// Crc 32 instructions of armv8.
+#[cfg(target_arch = "aarch64")]
+mod aarch64 {
+    use std::arch::aarch64::{
+        __crc32b,
+        __crc32d,
+    };
+
+    #[target_feature(enable = "crc")]
+    pub fn run(crc: u32, bytes: &[u8]) -> u32 {
+        let mut tmp = !crc;
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            tmp = __crc32d(tmp, u64::from_le_bytes(chunk.try_into().unwrap()));
+        }
+        for &b in chunks.remainder() {
+            tmp = __crc32b(tmp, b);
+        }
+        !tmp
+    }
+}
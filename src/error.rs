@@ -0,0 +1,124 @@
+use std::{
+    error::Error,
+    fmt,
+    io::{
+        self,
+        ErrorKind,
+    },
+};
+
+
+/// Everything that can go wrong when reading or writing a singleton zip.
+///
+/// It converts to and from `io::Error`, a `ZipError` wrapped into an `io::Error`
+/// (e.g. returned from the `Read` impl of `Reader`) is unwrapped when converted back.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ZipError {
+    Io(io::Error),
+    /// A header at `offset` does not start with the expected signature.
+    InvalidSignature { offset: u64, expected: [u8; 4], found: [u8; 4] },
+    /// No end of central directory record in the last `u16::MAX + 22` bytes.
+    EndOfCentralDirectoryNotFound,
+    /// The archive ends before the header or data starting at `offset`.
+    Truncated { offset: u64 },
+    /// Header values point to somewhere outside of the archive.
+    OutOfRange { offset: u64 },
+    /// Some fields are saturated but the zip64 extra field is missing or too short.
+    MissingZip64ExtraField,
+    NoEntry,
+    MultipleEntries { count: u64 },
+    UnsupportedCompressionMethod { method: u16 },
+    /// A stored entry followed by a data descriptor, its end is unknown without central directory.
+    StoredWithDataDescriptor,
+    Encrypted,
+    MultipleVolumes { disks: u32 },
+    ChecksumMismatch { expected: u32, found: u32 },
+    CompressedSizeMismatch { expected: u64, found: u64 },
+    UncompressedSizeMismatch { expected: u64, found: u64 },
+    NoFileName,
+    NameTooLong { length: usize },
+    /// Entry name is not a relative path with forward slashes only, `reason` tells which rule is broken.
+    InvalidFileName { name: String, reason: &'static str },
+    CommentTooLong { length: usize },
+    /// The archive comment contains the signature of end of central directory record,
+    /// readers searching backwards for the record could take the comment for it.
+    CommentContainsSignature,
+    /// Extra fields of a header take more than `u16::MAX` bytes in total.
+    ExtraFieldsTooLong { length: usize },
+}
+
+impl ZipError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(err) => err.kind(),
+            Self::Truncated { .. } => ErrorKind::UnexpectedEof,
+            Self::UnsupportedCompressionMethod { .. } | Self::StoredWithDataDescriptor | Self::Encrypted | Self::MultipleVolumes { .. } =>
+                ErrorKind::Unsupported,
+            Self::NoFileName | Self::NameTooLong { .. } | Self::InvalidFileName { .. } => ErrorKind::InvalidFilename,
+            Self::CommentTooLong { .. } | Self::CommentContainsSignature | Self::ExtraFieldsTooLong { .. } =>
+                ErrorKind::InvalidInput,
+            _ => ErrorKind::InvalidData,
+        }
+    }
+}
+
+impl fmt::Display for ZipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => err.fmt(f),
+            Self::InvalidSignature { offset, expected, found } =>
+                write!(f, "invalid signature at offset {offset}: expected {expected:02x?}, found {found:02x?}"),
+            Self::EndOfCentralDirectoryNotFound => f.write_str("end of central directory record not found"),
+            Self::Truncated { offset } => write!(f, "archive truncated at offset {offset}"),
+            Self::OutOfRange { offset } => write!(f, "offset {offset} is out of the archive"),
+            Self::MissingZip64ExtraField => f.write_str("missing zip64 extra field"),
+            Self::NoEntry => f.write_str("archive contains no entry"),
+            Self::MultipleEntries { count } => write!(f, "archive contains {count} entries, expected only one"),
+            Self::UnsupportedCompressionMethod { method } => write!(f, "unsupported compression method {method}"),
+            Self::StoredWithDataDescriptor => f.write_str("stored entry with data descriptor can only be read through central directory"),
+            Self::Encrypted => f.write_str("encrypted entry is not supported"),
+            Self::MultipleVolumes { disks } => write!(f, "archive spans {disks} volumes, only one is supported"),
+            Self::ChecksumMismatch { expected, found } =>
+                write!(f, "crc 32 mismatch: expected {expected:08x}, found {found:08x}"),
+            Self::CompressedSizeMismatch { expected, found } =>
+                write!(f, "compressed size mismatch: expected {expected}, found {found}"),
+            Self::UncompressedSizeMismatch { expected, found } =>
+                write!(f, "uncompressed size mismatch: expected {expected}, found {found}"),
+            Self::NoFileName => f.write_str("no file name"),
+            Self::NameTooLong { length } => write!(f, "file name too long: {length} bytes, at most {} bytes", u16::MAX),
+            Self::InvalidFileName { name, reason } => write!(f, "invalid file name {name:?}: {reason}"),
+            Self::CommentTooLong { length } => write!(f, "comment too long: {length} bytes, at most {} bytes", u16::MAX),
+            Self::CommentContainsSignature => write!(f, "archive comment contains the end of central directory signature"),
+            Self::ExtraFieldsTooLong { length } => write!(f, "extra fields too long: {length} bytes, at most {} bytes", u16::MAX),
+        }
+    }
+}
+
+impl Error for ZipError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ZipError {
+    fn from(err: io::Error) -> Self {
+        if err.get_ref().is_some_and(|inner| inner.is::<ZipError>()) {
+            *err.into_inner().unwrap().downcast::<ZipError>().unwrap()
+        } else {
+            Self::Io(err)
+        }
+    }
+}
+
+impl From<ZipError> for io::Error {
+    fn from(err: ZipError) -> Self {
+        match err {
+            ZipError::Io(err) => err,
+            err => io::Error::new(err.kind(), err),
+        }
+    }
+}
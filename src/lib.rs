@@ -1,57 +1,209 @@
 use std::{
-    ffi::OsString,
+    ffi::{
+        OsStr,
+        OsString,
+    },
     fs::File,
     io::{
         self,
-        ErrorKind,
+        BufReader,
+        Read,
         Seek,
         SeekFrom,
         Write,
     },
     path::Path,
+    time::SystemTime,
 };
 use flate2::{
-    read::DeflateDecoder,
+    bufread::DeflateDecoder,
     write::DeflateEncoder,
-    Compression,
 };
 
-mod common;
+pub mod common;
+mod cp437;
 mod crc32;
+mod error;
+mod parallel;
+mod pipeline;
+mod readahead;
+mod time;
+mod validate;
+
+pub use crate::common::ExtraField;
+pub use crate::crc32::Crc32;
+pub use crate::error::ZipError;
+pub use crate::pipeline::PipelinedWriter;
+pub use crate::time::{
+    DosDateTime,
+    NtfsTimes,
+};
+pub use crate::validate::{
+    validate,
+    Finding,
+};
+pub use flate2::Compression;
 
 use crate::common::{
     CentralDirectoryHeader,
+    DataDescriptor,
     EndOfCentralDirectoryRecord,
+    ExtendedTimestampExtraField,
     LocalFileHeader,
+    NtfsExtraField,
+    UnicodeCommentExtraField,
+    UnicodePathExtraField,
+    UnixExtraField,
     Zip64EndOfCentralDirectoryLocator,
     Zip64EndOfCentralDirectoryRecord,
     Zip64ExtraField,
     Zip64ExtraFieldSelect,
+    check_signature,
+    find_extra_field,
+    read_array,
+    truncated_at,
     COMPRESSION_METHOD_DEFLATE,
+    COMPRESSION_METHOD_STORED,
+    GENERAL_PURPOSE_BIT_FLAG_DATA_DESCRIPTOR,
     GENERAL_PURPOSE_BIT_FLAG_DEFLATE_NORMAL,
+    GENERAL_PURPOSE_BIT_FLAG_ENCRYPTED,
+    GENERAL_PURPOSE_BIT_FLAG_UTF8,
     TARGET_ZIP_VERSION,
+    VERSION_MADE_BY_UNIX,
 };
+use crate::parallel::ParallelDeflate;
+use crate::readahead::Readahead;
 
 
-pub struct Writer {
+pub struct Writer<W: Write> {
     crc_32: u32,
-    local_file_name: OsString,
-    deflate_writer: DeflateEncoder<File>,
-}
-impl Writer {
-    pub fn create(path: &Path) -> io::Result<Self> {
-        let local_file_name = match path.file_stem() {
-            None => return Err(io::Error::new(ErrorKind::InvalidFilename, "no file name")),
-            Some(s) => if s.len() > u16::MAX as usize {
-                return Err(io::Error::new(ErrorKind::InvalidFilename, "file name too long"));
-            } else {
-                s.to_os_string()
+    local_header_offset: u64,
+    /// File name as written in headers
+    file_name: Vec<u8>,
+    general_purpose_bit_flag: u16,
+    last_modified: DosDateTime,
+    version_made_by: u16,
+    external_file_attributes: u32,
+    file_comment: Vec<u8>,
+    archive_comment: Vec<u8>,
+    /// Extra fields in central directory header except zip64 extra field
+    central_extra_fields: Vec<u8>,
+    data_pos: u64,
+    /// `None` in streaming mode, the crc and sizes then go to a data descriptor
+    seek: Option<fn(&mut W, SeekFrom) -> io::Result<u64>>,
+    compression_method: u16,
+    uncompressed_size: u64,
+    encoder: Encoder<W>,
+}
+impl Writer<File> {
+    pub fn create(path: &Path) -> Result<Self, ZipError> {
+        WriterOptions::new().create(path)
+    }
+}
+impl<W: Write + Seek> Writer<W> {
+    /// Start writing a singleton zip into `sink` from its current position,
+    /// anything before that position is kept as a prefix of the archive.
+    pub fn new(sink: W, entry_name: impl AsRef<OsStr>) -> Result<Self, ZipError> {
+        WriterOptions::new().entry_name(entry_name).build(sink)
+    }
+}
+impl<W: Write> Writer<W> {
+    /// Write a singleton zip into a non-seekable `sink` (stdout, pipes, sockets...),
+    /// the archive is assumed to start at the current position of `sink`.
+    ///
+    /// The crc and sizes are written to a zip64 data descriptor after the compressed data
+    /// instead of being patched back into the local file header.
+    pub fn new_streaming(sink: W, entry_name: impl AsRef<OsStr>) -> Result<Self, ZipError> {
+        WriterOptions::new().entry_name(entry_name).build_streaming(sink)
+    }
+
+    fn start(
+        sink: W,
+        local_header_offset: u64,
+        options: WriterOptions,
+        seek: Option<fn(&mut W, SeekFrom) -> io::Result<u64>>,
+    ) -> Result<Self, ZipError> {
+        let entry_name = options.entry_name.ok_or(ZipError::NoFileName)?;
+        if entry_name.is_empty() {
+            return Err(ZipError::NoFileName);
+        }
+        // name that is not UTF-8 is written in code page 437 as far as possible,
+        // with its lossy UTF-8 version in Unicode path extra field, and so is the comment
+        let (file_name, file_comment, unicode_path, unicode_comment) = match entry_name.to_str() {
+            Some(name) => (name.as_bytes().to_vec(), options.comment.into_bytes(), None, None),
+            None => {
+                let file_name = cp437::encode(&entry_name.to_string_lossy());
+                let unicode_path = UnicodePathExtraField {
+                    header_id: UnicodePathExtraField::HEADER_ID,
+                    data_size: 0, // ignore, will auto set in `to_bytes`
+                    version: UnicodePathExtraField::VERSION,
+                    name_crc_32: crc32::run(0, &file_name),
+                    unicode_name: entry_name.to_string_lossy().into_owned().into_bytes(),
+                };
+                let file_comment = cp437::encode(&options.comment);
+                // an ASCII comment is the same in both
+                let unicode_comment = (!options.comment.is_ascii()).then(|| UnicodeCommentExtraField {
+                    header_id: UnicodeCommentExtraField::HEADER_ID,
+                    data_size: 0, // ignore, will auto set in `to_bytes`
+                    version: UnicodeCommentExtraField::VERSION,
+                    comment_crc_32: crc32::run(0, &file_comment),
+                    unicode_comment: options.comment.into_bytes(),
+                });
+                (file_name, file_comment, Some(unicode_path), unicode_comment)
+            },
+        };
+        if file_name.len() > u16::MAX as usize {
+            return Err(ZipError::NameTooLong { length: file_name.len() });
+        }
+        // the lossy UTF-8 name can be up to 3 times longer than the code page 437 one
+        if let Some(unicode_path) = unicode_path.as_ref() && unicode_path.unicode_name.len() > u16::MAX as usize - 5 {
+            return Err(ZipError::ExtraFieldsTooLong { length: 9 + unicode_path.unicode_name.len() });
+        }
+        check_file_name(&file_name)?;
+        if file_comment.len() > u16::MAX as usize {
+            return Err(ZipError::CommentTooLong { length: file_comment.len() });
+        }
+        if let Some(unicode_comment) = unicode_comment.as_ref() && unicode_comment.unicode_comment.len() > u16::MAX as usize - 5 {
+            return Err(ZipError::ExtraFieldsTooLong { length: 9 + unicode_comment.unicode_comment.len() });
+        }
+        let archive_comment = options.archive_comment.into_bytes();
+        if archive_comment.len() > u16::MAX as usize {
+            return Err(ZipError::CommentTooLong { length: archive_comment.len() });
+        }
+        if archive_comment.windows(4).any(|s| s == EndOfCentralDirectoryRecord::SIGNATURE) {
+            return Err(ZipError::CommentContainsSignature);
+        }
+        let modified = options.modified.unwrap_or_else(SystemTime::now);
+        let last_modified = DosDateTime::from_system_time(modified);
+        let extended_timestamp = time::to_unix_time(modified).map(|modification_time| ExtendedTimestampExtraField {
+            header_id: ExtendedTimestampExtraField::HEADER_ID,
+            data_size: 0, // ignore, will auto set in `to_bytes`
+            flags: ExtendedTimestampExtraField::FLAG_MODIFICATION_TIME,
+            modification_time,
+            access_time: 0,
+            creation_time: 0,
+        });
+        let (version_made_by, external_file_attributes) = match options.unix_permissions {
+            None => (TARGET_ZIP_VERSION, 0),
+            Some(mode) => {
+                // regular file if no file type is given
+                let mode = if mode & 0o170000 == 0 { mode | 0o100000 } else { mode };
+                (VERSION_MADE_BY_UNIX | TARGET_ZIP_VERSION, (mode & 0xFFFF) << 16)
             },
         };
-        let mut file = std::fs::File::create(path)?;
+        let mut general_purpose_bit_flag = if seek.is_some() {
+            GENERAL_PURPOSE_BIT_FLAG_DEFLATE_NORMAL
+        } else {
+            GENERAL_PURPOSE_BIT_FLAG_DEFLATE_NORMAL | GENERAL_PURPOSE_BIT_FLAG_DATA_DESCRIPTOR
+        };
+        if unicode_path.is_none() {
+            general_purpose_bit_flag |= GENERAL_PURPOSE_BIT_FLAG_UTF8;
+        }
+        let mut sink = CountingWriter { inner: sink, count: local_header_offset };
 
         // write local file header
-        let zip64_extra_field = Zip64ExtraField {
+        // zip64 extra field goes first, so its position is known when updating it
+        let mut local_extra_fields = Zip64ExtraField {
             header_id: Zip64ExtraField::HEADER_ID,
             data_size: 0, // ignore, will auto set in `select_to_bytes`
             uncompressed_size: 0, // placeholder
@@ -62,99 +214,222 @@ impl Writer {
             Zip64ExtraFieldSelect::UncompressedSize,
             Zip64ExtraFieldSelect::CompressedSize,
         ]);
-        let local_file_header = LocalFileHeader {
+        let mut central_extra_fields = Vec::new();
+        // fields given by the user replace the ones `Writer` would write with the same header id
+        let user_extra_fields = options.extra_fields.iter()
+            .filter(|field| !matches!(field, ExtraField::Zip64(_)))
+            .collect::<Vec<_>>();
+        let generated = |header_id: u16| user_extra_fields.iter().all(|field| field.header_id() != header_id);
+        if let Some(extended_timestamp) = extended_timestamp.as_ref() && generated(ExtendedTimestampExtraField::HEADER_ID) {
+            local_extra_fields.extend_from_slice(&extended_timestamp.to_bytes(false));
+            central_extra_fields.extend_from_slice(&extended_timestamp.to_bytes(true));
+        }
+        if let Some(ntfs_times) = options.ntfs_times.as_ref() && generated(NtfsExtraField::HEADER_ID) {
+            let ntfs_extra_field = NtfsExtraField {
+                header_id: NtfsExtraField::HEADER_ID,
+                data_size: 32,
+                reserved: 0,
+                modification_time: time::to_filetime(ntfs_times.modified),
+                access_time: time::to_filetime(ntfs_times.accessed),
+                creation_time: time::to_filetime(ntfs_times.created),
+            }.to_bytes();
+            local_extra_fields.extend_from_slice(&ntfs_extra_field);
+            central_extra_fields.extend_from_slice(&ntfs_extra_field);
+        }
+        if let Some((uid, gid)) = options.unix_owner && generated(UnixExtraField::HEADER_ID) {
+            let unix_extra_field = UnixExtraField {
+                header_id: UnixExtraField::HEADER_ID,
+                data_size: 11,
+                version: UnixExtraField::VERSION,
+                uid,
+                gid,
+            }.to_bytes();
+            local_extra_fields.extend_from_slice(&unix_extra_field);
+            central_extra_fields.extend_from_slice(&unix_extra_field);
+        }
+        if let Some(unicode_path) = unicode_path.as_ref() && generated(UnicodePathExtraField::HEADER_ID) {
+            let unicode_path_extra_field = unicode_path.to_bytes();
+            local_extra_fields.extend_from_slice(&unicode_path_extra_field);
+            central_extra_fields.extend_from_slice(&unicode_path_extra_field);
+        }
+        // the comment is only in central directory header
+        if let Some(unicode_comment) = unicode_comment.as_ref() && generated(UnicodeCommentExtraField::HEADER_ID) {
+            central_extra_fields.extend_from_slice(&unicode_comment.to_bytes());
+        }
+        for extra_field in user_extra_fields {
+            let data_size = match extra_field {
+                ExtraField::UnicodePath(field) => 5 + field.unicode_name.len(),
+                ExtraField::UnicodeComment(field) => 5 + field.unicode_comment.len(),
+                ExtraField::Unknown { data, .. } => data.len(),
+                _ => 0, // fixed sizes
+            };
+            if data_size > u16::MAX as usize {
+                return Err(ZipError::ExtraFieldsTooLong { length: 4 + data_size });
+            }
+            local_extra_fields.extend_from_slice(&extra_field.to_bytes(false));
+            central_extra_fields.extend_from_slice(&extra_field.to_bytes(true));
+        }
+        // central directory header may get a zip64 extra field of 28 bytes
+        let extra_fields_length = local_extra_fields.len().max(central_extra_fields.len() + 28);
+        if extra_fields_length > u16::MAX as usize {
+            return Err(ZipError::ExtraFieldsTooLong { length: extra_fields_length });
+        }
+        let mut local_file_header = LocalFileHeader {
             signature: LocalFileHeader::SIGNATURE,
             version_needed_to_extract: TARGET_ZIP_VERSION,
-            general_purpose_bit_flag: GENERAL_PURPOSE_BIT_FLAG_DEFLATE_NORMAL,
-            compression_method: COMPRESSION_METHOD_DEFLATE,
-            last_modified_file_time: 0, // nobody cares
-            last_modified_file_date: 0, // nobody cares
+            general_purpose_bit_flag,
+            compression_method: options.compression_method.id(),
+            last_modified_file_time: last_modified.time,
+            last_modified_file_date: last_modified.date,
             crc_32: 0, // placeholder
             compressed_size: u32::MAX, // actual value is stored in zip64 extra field
             uncompressed_size: u32::MAX, // actual value is stored in zip64 extra field
-            file_name_length: local_file_name.len() as u16,
-            extra_field_length: zip64_extra_field.len() as u16,
-        }.to_bytes();
-        file.write_all(&local_file_header)?;
-        file.write_all(&local_file_name.as_encoded_bytes())?;
-        file.write_all(&zip64_extra_field)?;
+            file_name_length: file_name.len() as u16,
+            extra_field_length: local_extra_fields.len() as u16,
+        }.to_bytes().to_vec();
+        local_file_header.extend_from_slice(&file_name);
+        local_file_header.extend_from_slice(&local_extra_fields);
+        let data_pos = local_header_offset + local_file_header.len() as u64;
+
+        let encoder = match (options.compression_method, options.fallback_sample_size) {
+            (CompressionMethod::Deflate, Some(sample_size)) => {
+                // local file header is written after the compression method is decided
+                Encoder::Sampling(Box::new(Sampling {
+                    sink,
+                    local_file_header,
+                    sample: Vec::new(),
+                    sample_size,
+                    compression: options.compression,
+                    threads: options.threads,
+                }))
+            },
+            (CompressionMethod::Stored, _) => {
+                sink.write_all(&local_file_header)?;
+                Encoder::Stored(sink)
+            },
+            (CompressionMethod::Deflate, None) => {
+                sink.write_all(&local_file_header)?;
+                Encoder::deflate(sink, options.compression, options.threads)
+            },
+        };
 
         Ok(Self {
             crc_32: 0,
-            local_file_name,
-            deflate_writer: DeflateEncoder::new(file, Compression::best()),
+            local_header_offset,
+            file_name,
+            general_purpose_bit_flag,
+            last_modified,
+            version_made_by,
+            external_file_attributes,
+            file_comment,
+            archive_comment,
+            central_extra_fields,
+            data_pos,
+            seek,
+            compression_method: options.compression_method.id(),
+            uncompressed_size: 0,
+            encoder,
         })
     }
 
-    pub fn finish(mut self) -> io::Result<File> {
-        self.deflate_writer.flush()?;
-        let uncompressed_size = self.deflate_writer.total_in();
-        let compressed_size = self.deflate_writer.total_out() + 2; // will write 0x0003 when deflate close
-        let mut file = self.deflate_writer.finish()?;
-
-        // update local file header
-        let cd_pos = file.stream_position()?;
-            // update crc 32
-            file.seek(SeekFrom::Start(14))?;
-            file.write_all(&self.crc_32.to_le_bytes())?;
-            // update uncompressed size
-            file.seek(SeekFrom::Current(self.local_file_name.len() as i64 + 16))?;
-            file.write_all(&uncompressed_size.to_le_bytes())?;
-            // update compressed size
-            file.write_all(&compressed_size.to_le_bytes())?;
-        file.seek(SeekFrom::Start(cd_pos))?;
+    /// Decide the compression method from the sample if it is still being collected.
+    fn end_sampling(&mut self) -> io::Result<()> {
+        if !matches!(self.encoder, Encoder::Sampling(_)) {
+            return Ok(());
+        }
+        let Encoder::Sampling(sampling) = std::mem::replace(&mut self.encoder, Encoder::Poisoned) else {
+            unreachable!()
+        };
+        let (encoder, compression_method) = sampling.decide()?;
+        self.encoder = encoder;
+        self.compression_method = compression_method.id();
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<W, ZipError> {
+        self.end_sampling()?;
+        let uncompressed_size = self.uncompressed_size;
+        let (mut sink, crc_32) = self.encoder.finish()?;
+        if let Some(crc_32) = crc_32 {
+            self.crc_32 = crc_32;
+        }
+        let compressed_size = sink.count - self.data_pos;
+
+        if let Some(seek) = self.seek {
+            // update local file header
+            let cd_pos = sink.count;
+                // update crc 32
+                seek(&mut sink.inner, SeekFrom::Start(self.local_header_offset + 14))?;
+                sink.inner.write_all(&self.crc_32.to_le_bytes())?;
+                // update uncompressed size
+                seek(&mut sink.inner, SeekFrom::Current(self.file_name.len() as i64 + 16))?;
+                sink.inner.write_all(&uncompressed_size.to_le_bytes())?;
+                // update compressed size
+                sink.inner.write_all(&compressed_size.to_le_bytes())?;
+            seek(&mut sink.inner, SeekFrom::Start(cd_pos))?;
+        } else {
+            // write data descriptor
+            let data_descriptor = DataDescriptor {
+                signature: DataDescriptor::SIGNATURE,
+                crc_32: self.crc_32,
+                compressed_size,
+                uncompressed_size,
+            }.to_bytes();
+            sink.write_all(&data_descriptor)?;
+        }
+        let cd_pos = sink.count;
 
         // write central directory
         // write central directory header
         let use_zip64_cd_header =
             compressed_size >= u32::MAX as u64 ||
             uncompressed_size >= u32::MAX as u64 ||
-            cd_pos >= u32::MAX as u64;
-        let zip64_extra_field = if use_zip64_cd_header {
-            Some(Zip64ExtraField {
+            self.local_header_offset >= u32::MAX as u64;
+        let mut extra_fields = if use_zip64_cd_header {
+            Zip64ExtraField {
                 header_id: Zip64ExtraField::HEADER_ID,
                 data_size: 0, // ignore, will auto set in `select_to_bytes`
                 uncompressed_size,
                 compressed_size,
-                relative_offset_of_local_header: cd_pos as u64, // reverse relative offset to file start
+                relative_offset_of_local_header: self.local_header_offset,
                 disk_number_start: 0, // no multiple volume
             }.select_to_bytes(&[
                 Zip64ExtraFieldSelect::UncompressedSize,
                 Zip64ExtraFieldSelect::CompressedSize,
                 Zip64ExtraFieldSelect::RelativeOffsetOfLocalHeader,
-            ]))
-        } else { None };
+            ])
+        } else { Vec::new() };
+        extra_fields.extend_from_slice(&self.central_extra_fields);
         let central_directory_header = CentralDirectoryHeader {
             signature: CentralDirectoryHeader::SIGNATURE,
-            version_made_by: TARGET_ZIP_VERSION,
+            version_made_by: self.version_made_by,
             version_needed_to_extract: TARGET_ZIP_VERSION,
-            general_purpose_bit_flag: GENERAL_PURPOSE_BIT_FLAG_DEFLATE_NORMAL,
-            compression_method: COMPRESSION_METHOD_DEFLATE,
-            last_modified_file_time: 0, // nobody cares
-            last_modified_file_date: 0, // nobody cares
+            general_purpose_bit_flag: self.general_purpose_bit_flag,
+            compression_method: self.compression_method,
+            last_modified_file_time: self.last_modified.time,
+            last_modified_file_date: self.last_modified.date,
             crc_32: self.crc_32,
-            compressed_size: if zip64_extra_field.is_some() { u32::MAX } else { compressed_size as u32 },
-            uncompressed_size: if zip64_extra_field.is_some() { u32::MAX } else { uncompressed_size as u32 },
-            file_name_length: self.local_file_name.len() as u16,
-            extra_field_length: if let Some(z64) = zip64_extra_field.as_ref() { z64.len() as u16 } else { 0 },
-            file_comment_length: 0, // no comment
+            compressed_size: if use_zip64_cd_header { u32::MAX } else { compressed_size as u32 },
+            uncompressed_size: if use_zip64_cd_header { u32::MAX } else { uncompressed_size as u32 },
+            file_name_length: self.file_name.len() as u16,
+            extra_field_length: extra_fields.len() as u16,
+            file_comment_length: self.file_comment.len() as u16,
             disk_number_start: 0, // no multiple volumes
             internal_file_attributes: 0,
-            external_file_attributes: 0,
-            relative_offset_of_local_header: if zip64_extra_field.is_some() { u32::MAX } else { cd_pos as u32 },
+            external_file_attributes: self.external_file_attributes,
+            relative_offset_of_local_header: if use_zip64_cd_header { u32::MAX } else { self.local_header_offset as u32 },
         }.to_bytes();
-        file.write_all(&central_directory_header)?;
-        file.write_all(&self.local_file_name.as_encoded_bytes())?;
-        if let Some(z64) = zip64_extra_field.as_ref() {
-            file.write_all(&z64)?;
-        }
-        let cd_size = file.stream_position()? - cd_pos;
+        sink.write_all(&central_directory_header)?;
+        sink.write_all(&self.file_name)?;
+        sink.write_all(&extra_fields)?;
+        sink.write_all(&self.file_comment)?;
+        let cd_size = sink.count - cd_pos;
 
         // write end of central direction
         let use_zip64_ending = cd_pos >= u32::MAX as u64;
         if use_zip64_ending {
             // write zip64 end of central directory record
-            let rec_pos = file.stream_position()?;
+            let rec_pos = sink.count;
             let zip64_end_of_central_directory_record = Zip64EndOfCentralDirectoryRecord {
                 signature: Zip64EndOfCentralDirectoryRecord::SIGNATURE,
                 size_of_zip64_end_of_central_directory_record: 44,
@@ -167,15 +442,15 @@ impl Writer {
                 size_of_the_central_directory: cd_size,
                 offset_of_start_of_central_directory_with_respect_to_the_starting_disk_number: cd_pos,
             }.to_bytes();
-            file.write_all(&zip64_end_of_central_directory_record)?;
+            sink.write_all(&zip64_end_of_central_directory_record)?;
             // write zip64 end of central directory locator
             let zip64_end_of_central_directory_locator = Zip64EndOfCentralDirectoryLocator {
                 signature: Zip64EndOfCentralDirectoryLocator::SIGNATURE,
-                number_of_the_disk_with_the_start_of_the_zip64_end_of_central_irectory: 0,
+                number_of_the_disk_with_the_start_of_the_zip64_end_of_central_directory: 0,
                 relative_offset_of_the_zip64_end_of_central_directory_record: rec_pos,
                 total_number_of_disks: 1,
             }.to_bytes();
-            file.write_all(&zip64_end_of_central_directory_locator)?;
+            sink.write_all(&zip64_end_of_central_directory_locator)?;
         }
 
         // write end of central directory record
@@ -187,62 +462,914 @@ impl Writer {
             total_number_of_entries_in_the_central_directory_on_this_disk: if use_zip64_ending { u16::MAX } else { 1 },
             size_of_the_central_directory: if use_zip64_ending { u32::MAX } else { cd_size as u32 },
             offset_of_start_of_central_directory_with_respect_to_the_starting_disk_number: if use_zip64_ending { u32::MAX } else { cd_pos as u32 },
-            zip_file_comment_length: 0,
+            zip_file_comment_length: self.archive_comment.len() as u16,
         }.to_bytes();
-        file.write_all(&end_of_central_directory_record)?;
+        sink.write_all(&end_of_central_directory_record)?;
+        sink.write_all(&self.archive_comment)?;
 
-        Ok(file)
+        Ok(sink.inner)
     }
 }
 
-impl io::Write for Writer {
+impl<W: Write> io::Write for Writer<W> {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        let len = self.deflate_writer.write(buf)?;
-        self.crc_32 = crc32::run(self.crc_32, &buf[0..len]);
+        if let Encoder::Sampling(sampling) = &self.encoder && sampling.sample.len() >= sampling.sample_size {
+            self.end_sampling()?;
+        }
+        let len = self.encoder.write(buf)?;
+        // parallel deflate computes the crc 32 on its workers
+        if !matches!(self.encoder, Encoder::Parallel(_)) {
+            self.crc_32 = crc32::run(self.crc_32, &buf[0..len]);
+        }
+        self.uncompressed_size += len as u64;
         Ok(len)
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
-        self.deflate_writer.flush()
+        self.encoder.flush()
+    }
+}
+
+
+enum Encoder<W: Write> {
+    Stored(CountingWriter<W>),
+    Deflate(DeflateEncoder<CountingWriter<W>>),
+    Parallel(Box<ParallelDeflate<W>>),
+    Sampling(Box<Sampling<W>>),
+    /// Left behind when switching from `Sampling` failed
+    Poisoned,
+}
+impl<W: Write> Encoder<W> {
+    fn deflate(sink: CountingWriter<W>, compression: Compression, threads: usize) -> Self {
+        if threads > 1 {
+            Self::Parallel(Box::new(ParallelDeflate::new(sink, compression, threads)))
+        } else {
+            Self::Deflate(DeflateEncoder::new(sink, compression))
+        }
+    }
+
+    /// Also returns the crc 32 of the entry if it is computed by the encoder.
+    fn finish(self) -> io::Result<(CountingWriter<W>, Option<u32>)> {
+        match self {
+            Self::Stored(sink) => Ok((sink, None)),
+            Self::Deflate(encoder) => Ok((encoder.finish()?, None)),
+            Self::Parallel(encoder) => {
+                let (sink, crc_32) = encoder.finish()?;
+                Ok((sink, Some(crc_32)))
+            },
+            Self::Sampling(_) => unreachable!("sampling is ended before finish"),
+            Self::Poisoned => Err(poisoned_error()),
+        }
+    }
+}
+impl<W: Write> io::Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Stored(sink) => sink.write(buf),
+            Self::Deflate(encoder) => encoder.write(buf),
+            Self::Parallel(encoder) => encoder.write(buf),
+            Self::Sampling(sampling) => {
+                let len = buf.len().min(sampling.sample_size - sampling.sample.len());
+                sampling.sample.extend_from_slice(&buf[..len]);
+                Ok(len)
+            },
+            Self::Poisoned => Err(poisoned_error()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Stored(sink) => sink.flush(),
+            Self::Deflate(encoder) => encoder.flush(),
+            Self::Parallel(encoder) => encoder.flush(),
+            Self::Sampling(_) => Ok(()), // nothing can be written before the method is decided
+            Self::Poisoned => Err(poisoned_error()),
+        }
+    }
+}
+
+fn poisoned_error() -> io::Error {
+    io::Error::other("writer is poisoned by a previous error")
+}
+
+/// Beginning of the entry held back to see if deflate pays off.
+struct Sampling<W: Write> {
+    sink: CountingWriter<W>,
+    local_file_header: Vec<u8>,
+    sample: Vec<u8>,
+    sample_size: usize,
+    compression: Compression,
+    threads: usize,
+}
+impl<W: Write> Sampling<W> {
+    /// Write the local file header with the chosen method then the sample,
+    /// the sample is compressed twice if deflate is chosen.
+    fn decide(mut self) -> io::Result<(Encoder<W>, CompressionMethod)> {
+        let mut trial = DeflateEncoder::new(Vec::new(), self.compression);
+        trial.write_all(&self.sample)?;
+        let compression_method = if trial.finish()?.len() < self.sample.len() {
+            CompressionMethod::Deflate
+        } else {
+            CompressionMethod::Stored
+        };
+        self.local_file_header[8..10].copy_from_slice(&compression_method.id().to_le_bytes());
+        self.sink.write_all(&self.local_file_header)?;
+        let mut encoder = match compression_method {
+            CompressionMethod::Stored => Encoder::Stored(self.sink),
+            CompressionMethod::Deflate => Encoder::deflate(self.sink, self.compression, self.threads),
+        };
+        encoder.write_all(&self.sample)?;
+        Ok((encoder, compression_method))
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompressionMethod {
+    /// No compression, for data that is already compressed.
+    Stored,
+    Deflate,
+}
+impl CompressionMethod {
+    fn id(self) -> u16 {
+        match self {
+            Self::Stored => COMPRESSION_METHOD_STORED,
+            Self::Deflate => COMPRESSION_METHOD_DEFLATE,
+        }
+    }
+
+    fn from_id(id: u16) -> Result<Self, ZipError> {
+        match id {
+            COMPRESSION_METHOD_STORED => Ok(Self::Stored),
+            COMPRESSION_METHOD_DEFLATE => Ok(Self::Deflate),
+            method => Err(ZipError::UnsupportedCompressionMethod { method }),
+        }
+    }
+}
+
+
+/// Options of `Writer`, the default is what `Writer::create` uses:
+/// deflate with best compression, modified now, no comment and no permissions.
+#[derive(Clone, Debug)]
+pub struct WriterOptions {
+    compression_method: CompressionMethod,
+    compression: Compression,
+    threads: usize,
+    fallback_sample_size: Option<usize>,
+    entry_name: Option<OsString>,
+    modified: Option<SystemTime>,
+    ntfs_times: Option<NtfsTimes>,
+    comment: String,
+    archive_comment: String,
+    unix_permissions: Option<u32>,
+    unix_owner: Option<(u32, u32)>,
+    extra_fields: Vec<ExtraField>,
+}
+impl Default for WriterOptions {
+    fn default() -> Self {
+        Self {
+            compression_method: CompressionMethod::Deflate,
+            compression: Compression::best(),
+            threads: 1,
+            fallback_sample_size: None,
+            entry_name: None,
+            modified: None,
+            ntfs_times: None,
+            comment: String::new(),
+            archive_comment: String::new(),
+            unix_permissions: None,
+            unix_owner: None,
+            extra_fields: Vec::new(),
+        }
+    }
+}
+impl WriterOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stored entries can not be read back by a streaming reader when written in streaming mode,
+    /// since their end is unknown until the central directory.
+    pub fn compression_method(mut self, compression_method: CompressionMethod) -> Self {
+        self.compression_method = compression_method;
+        self
+    }
+
+    /// Compression level of deflate, `Compression::fast()`, `Compression::default()`, `Compression::best()`
+    /// or any level from `Compression::new(0)` to `Compression::new(9)`.
+    ///
+    /// Only the level can be chosen, the deflate strategy (filtered, huffman only, RLE...) is not exposed by flate2
+    /// with any of its backends, so there is no option for it.
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Compress on `threads` worker threads, 0 or 1 keeps deflate on the thread writing into `Writer`.
+    ///
+    /// The entry is split into 128 KiB blocks compressed independently with the last 32 KiB
+    /// of the previous block as dictionary, the result is slightly larger than single threaded deflate.
+    /// The backend of flate2 used here can not set a dictionary, so each worker compresses those 32 KiB
+    /// again and throws the output away, costing about a quarter more CPU time in total than single threaded deflate.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Fall back to stored if deflate does not shrink the first `sample_size` bytes of the entry.
+    ///
+    /// Nothing is written into the sink until the sample is collected, so it can be used in streaming mode,
+    /// but like `CompressionMethod::Stored` a stored entry written in streaming mode
+    /// can not be read back by a streaming reader.
+    pub fn fallback_to_stored(mut self, sample_size: usize) -> Self {
+        self.fallback_sample_size = Some(sample_size);
+        self
+    }
+
+    /// Name of the entry, required unless writing to a path where it defaults to the file stem.
+    ///
+    /// It is a relative path separated by forward slashes (e.g. `data/2026/report.csv`),
+    /// backslashes, absolute paths, drive letters, a trailing slash, and empty, `.` or `..` components
+    /// are rejected when building.
+    ///
+    /// A UTF-8 name is written as is and flagged, otherwise it is written in code page 437
+    /// with its lossy UTF-8 version in Unicode path extra field, see `comment` for the comment.
+    pub fn entry_name(mut self, entry_name: impl AsRef<OsStr>) -> Self {
+        self.entry_name = Some(entry_name.as_ref().to_os_string());
+        self
+    }
+
+    /// Last modification time of the entry, default is when the writer is built.
+    ///
+    /// It is stored as MS-DOS date and time, and in extended timestamp extra field with 1 second precision.
+    pub fn modified(mut self, modified: SystemTime) -> Self {
+        self.modified = Some(modified);
+        self
+    }
+
+    /// Write modification, access and creation times into NTFS extra field, with 100 nanoseconds precision.
+    ///
+    /// It does not change the time set by `modified`.
+    pub fn ntfs_times(mut self, ntfs_times: NtfsTimes) -> Self {
+        self.ntfs_times = Some(ntfs_times);
+        self
+    }
+
+    /// Comment of the entry in central directory, at most `u16::MAX` bytes.
+    ///
+    /// It shares the encoding of the entry name: with a name that is not UTF-8 it is written in code page 437
+    /// as far as possible, and a comment that is not ASCII is also written in full in Unicode comment extra field.
+    pub fn comment(mut self, comment: impl Into<String>) -> Self {
+        self.comment = comment.into();
+        self
+    }
+
+    /// Comment of the whole archive after end of central directory record, at most `u16::MAX` bytes.
+    ///
+    /// Zip has no flag for its encoding, it is written in UTF-8.
+    /// It must not contain `PK\x05\x06`, the signature of end of central directory record.
+    pub fn archive_comment(mut self, comment: impl Into<String>) -> Self {
+        self.archive_comment = comment.into();
+        self
+    }
+
+    /// Unix mode of the entry (e.g. `0o755`), the archive is then marked as made on Unix.
+    pub fn unix_permissions(mut self, mode: u32) -> Self {
+        self.unix_permissions = Some(mode);
+        self
+    }
+
+    /// Unix uid and gid of the entry, stored in Info-ZIP new Unix extra field.
+    pub fn unix_owner(mut self, uid: u32, gid: u32) -> Self {
+        self.unix_owner = Some((uid, gid));
+        self
+    }
+
+    /// Add an extra field to both local file header and central directory header,
+    /// after the ones written by `Writer`.
+    ///
+    /// Zip64 extra field is managed by `Writer` and ignored here, any other field written by `Writer`
+    /// (extended timestamp, NTFS, Unix, Unicode path) is replaced by a given field with the same header id.
+    pub fn extra_field(mut self, extra_field: ExtraField) -> Self {
+        self.extra_fields.push(extra_field);
+        self
+    }
+
+    /// Start writing into `sink` from its current position, see `Writer::new`.
+    pub fn build<W: Write + Seek>(self, mut sink: W) -> Result<Writer<W>, ZipError> {
+        let local_header_offset = sink.stream_position()?;
+        Writer::start(sink, local_header_offset, self, Some(W::seek))
+    }
+
+    /// Start writing into a non-seekable `sink`, see `Writer::new_streaming`.
+    pub fn build_streaming<W: Write>(self, sink: W) -> Result<Writer<W>, ZipError> {
+        Writer::start(sink, 0, self, None)
+    }
+
+    /// Create a file at `path` and start writing into it,
+    /// the entry name defaults to the file stem of `path`.
+    pub fn create(mut self, path: &Path) -> Result<Writer<File>, ZipError> {
+        if self.entry_name.is_none() {
+            self.entry_name = Some(path.file_stem().ok_or(ZipError::NoFileName)?.to_os_string());
+        }
+        self.build(File::create(path)?)
     }
 }
 
 
-/// Read a zip file that save by `singletonzip` itself,
-/// this is not a general zip file reader.
-pub struct Reader {
-    deflate_reader: DeflateDecoder<File>,
+/// Read a zip file that contains exactly one entry,
+/// the entry is located through the central directory so archives from other tools work too.
+pub struct Reader<R: Read> {
+    decoder: Decoder<R>,
+    file_name: String,
+    /// Comment of the entry, only in central directory
+    file_comment: String,
+    /// Comment after end of central directory record
+    archive_comment: String,
+    crc_32: Option<u32>,
+    compressed_size: Option<u64>,
+    uncompressed_size: Option<u64>,
+    last_modified: DosDateTime,
+    /// Modification time in extended timestamp extra field
+    modified: Option<SystemTime>,
+    ntfs_times: Option<NtfsTimes>,
+    /// Unix mode from external file attributes, only in central directory
+    unix_permissions: Option<u32>,
+    unix_owner: Option<(u32, u32)>,
+    /// Extra fields in central directory header, or in local file header when reading without central directory
+    extra_fields: Vec<ExtraField>,
+    data_descriptor: DataDescriptorKind,
+    verify: bool,
+    running_crc_32: u32,
 }
-impl Reader {
-    pub fn open(path: &Path) -> io::Result<Reader> {
-        let mut file = File::open(path)?;
-        file.seek(SeekFrom::Current(26))?;
-        let file_name_length = read_u16(&mut file)? as i64;
-        let extra_field_length = read_u16(&mut file)? as i64;
-        file.seek(SeekFrom::Current(file_name_length + extra_field_length))?;
+impl Reader<File> {
+    pub fn open(path: &Path) -> Result<Self, ZipError> {
+        Self::new(File::open(path)?)
+    }
+}
+impl<R: Read + Seek> Reader<R> {
+    pub fn new(mut source: R) -> Result<Self, ZipError> {
+        let (eocd_pos, eocd, archive_comment) = find_end_of_central_directory(&mut source)?;
+
+        let mut number_of_entries = eocd.total_number_of_entries_in_the_central_directory as u64;
+        let mut cd_size = eocd.size_of_the_central_directory as u64;
+        let mut cd_pos = eocd.offset_of_start_of_central_directory_with_respect_to_the_starting_disk_number as u64;
+        let mut cd_end_pos = eocd_pos;
+        // find zip64 end of central directory locator and record, they may exist even if no field in eocd is saturated
+        if eocd_pos >= 20 {
+            let locator = Zip64EndOfCentralDirectoryLocator::from_bytes(&read_array_at(&mut source, eocd_pos - 20)?);
+            if locator.signature == Zip64EndOfCentralDirectoryLocator::SIGNATURE {
+                if locator.total_number_of_disks > 1 {
+                    return Err(ZipError::MultipleVolumes { disks: locator.total_number_of_disks });
+                }
+                let mut record_pos = locator.relative_offset_of_the_zip64_end_of_central_directory_record;
+                let mut record = Zip64EndOfCentralDirectoryRecord::from_bytes(&read_array_at(&mut source, record_pos)?);
+                if record.signature != Zip64EndOfCentralDirectoryRecord::SIGNATURE && eocd_pos >= 20 + 56 {
+                    // archive has been prefixed, the record without extensible data sector is right before the locator
+                    record_pos = eocd_pos - 20 - 56;
+                    record = Zip64EndOfCentralDirectoryRecord::from_bytes(&read_array_at(&mut source, record_pos)?);
+                }
+                check_signature(record_pos, Zip64EndOfCentralDirectoryRecord::SIGNATURE, record.signature)?;
+                number_of_entries = record.total_number_of_entries_in_the_central_directory;
+                cd_size = record.size_of_the_central_directory;
+                cd_pos = record.offset_of_start_of_central_directory_with_respect_to_the_starting_disk_number;
+                cd_end_pos = record_pos;
+            }
+        }
+        match number_of_entries {
+            0 => return Err(ZipError::NoEntry),
+            1 => {},
+            count => return Err(ZipError::MultipleEntries { count }),
+        }
+        // offsets are relative to the archive start, which is not the file start if something is prepended to it
+        let archive_offset = cd_end_pos.checked_sub(cd_pos.saturating_add(cd_size))
+            .ok_or(ZipError::OutOfRange { offset: cd_pos })?;
+        cd_pos += archive_offset;
+
+        // read central directory header
+        source.seek(SeekFrom::Start(cd_pos))?;
+        let cd_header = CentralDirectoryHeader::read_from(&mut source, cd_pos)?;
+        let mut file_name = vec![0u8; cd_header.file_name_length as usize];
+        source.read_exact(&mut file_name).map_err(|err| truncated_at(err, cd_pos))?;
+        let mut extra_fields = vec![0u8; cd_header.extra_field_length as usize];
+        source.read_exact(&mut extra_fields).map_err(|err| truncated_at(err, cd_pos))?;
+        let mut file_comment = vec![0u8; cd_header.file_comment_length as usize];
+        source.read_exact(&mut file_comment).map_err(|err| truncated_at(err, cd_pos))?;
+
+        let mut uncompressed_size = cd_header.uncompressed_size as u64;
+        let mut compressed_size = cd_header.compressed_size as u64;
+        let mut local_header_pos = cd_header.relative_offset_of_local_header as u64;
+        let mut selected = Vec::new();
+        if cd_header.uncompressed_size == u32::MAX { selected.push(Zip64ExtraFieldSelect::UncompressedSize); }
+        if cd_header.compressed_size == u32::MAX { selected.push(Zip64ExtraFieldSelect::CompressedSize); }
+        if cd_header.relative_offset_of_local_header == u32::MAX { selected.push(Zip64ExtraFieldSelect::RelativeOffsetOfLocalHeader); }
+        if cd_header.disk_number_start == u16::MAX { selected.push(Zip64ExtraFieldSelect::DiskNumberStart); }
+        if !selected.is_empty() {
+            let zip64_extra_field = find_extra_field(&extra_fields, Zip64ExtraField::HEADER_ID)
+                .and_then(|bytes| Zip64ExtraField::select_from_bytes(bytes, &selected))
+                .ok_or(ZipError::MissingZip64ExtraField)?;
+            if cd_header.uncompressed_size == u32::MAX { uncompressed_size = zip64_extra_field.uncompressed_size; }
+            if cd_header.compressed_size == u32::MAX { compressed_size = zip64_extra_field.compressed_size; }
+            if cd_header.relative_offset_of_local_header == u32::MAX { local_header_pos = zip64_extra_field.relative_offset_of_local_header; }
+        }
+        local_header_pos += archive_offset;
+
+        if cd_header.general_purpose_bit_flag & GENERAL_PURPOSE_BIT_FLAG_ENCRYPTED != 0 {
+            return Err(ZipError::Encrypted);
+        }
+        let compression_method = CompressionMethod::from_id(cd_header.compression_method)?;
+
+        // skip local file header, its sizes are not trusted since they may be in a data descriptor
+        source.seek(SeekFrom::Start(local_header_pos))?;
+        let local_header = LocalFileHeader::read_from(&mut source, local_header_pos)?;
+        let data_pos = local_header_pos + 30 + local_header.file_name_length as u64 + local_header.extra_field_length as u64;
+        if data_pos.saturating_add(compressed_size) > cd_pos {
+            return Err(ZipError::OutOfRange { offset: data_pos });
+        }
+        source.seek(SeekFrom::Start(data_pos))?;
 
         Ok(Self {
-            deflate_reader: DeflateDecoder::new(file),
+            decoder: Decoder::new(compression_method, source.take(compressed_size)),
+            file_name: decode_file_name(&file_name, cd_header.general_purpose_bit_flag, &extra_fields),
+            file_comment: decode_file_comment(&file_comment, cd_header.general_purpose_bit_flag, &extra_fields),
+            // no flag tells the encoding, take it as UTF-8 if it is valid
+            archive_comment: String::from_utf8(archive_comment).unwrap_or_else(|err| cp437::decode(err.as_bytes())),
+            crc_32: Some(cd_header.crc_32),
+            compressed_size: Some(compressed_size),
+            uncompressed_size: Some(uncompressed_size),
+            last_modified: DosDateTime { time: cd_header.last_modified_file_time, date: cd_header.last_modified_file_date },
+            modified: extended_modification_time(&extra_fields),
+            ntfs_times: ntfs_times(&extra_fields),
+            unix_permissions: Some(cd_header.external_file_attributes >> 16)
+                .filter(|&mode| cd_header.version_made_by >> 8 == VERSION_MADE_BY_UNIX >> 8 && mode != 0),
+            unix_owner: unix_owner(&extra_fields),
+            extra_fields: ExtraField::parse_all(&extra_fields),
+            data_descriptor: DataDescriptorKind::None, // values in central directory are final
+            verify: true,
+            running_crc_32: 0,
+        })
+    }
+}
+impl<R: Read> Reader<R> {
+    /// Read a singleton zip from a non-seekable `source` (stdin, pipes, sockets...),
+    /// the local file header is expected at the current position of `source`.
+    ///
+    /// If the entry is followed by a data descriptor, the crc and sizes are only known
+    /// after the whole entry is read.
+    pub fn new_streaming(mut source: R) -> Result<Self, ZipError> {
+        let local_header = LocalFileHeader::read_from(&mut source, 0)?;
+        if local_header.general_purpose_bit_flag & GENERAL_PURPOSE_BIT_FLAG_ENCRYPTED != 0 {
+            return Err(ZipError::Encrypted);
+        }
+        let compression_method = CompressionMethod::from_id(local_header.compression_method)?;
+        let has_data_descriptor = local_header.general_purpose_bit_flag & GENERAL_PURPOSE_BIT_FLAG_DATA_DESCRIPTOR != 0;
+        if has_data_descriptor && compression_method == CompressionMethod::Stored {
+            return Err(ZipError::StoredWithDataDescriptor);
+        }
+        let mut file_name_and_extra_fields = vec![0u8; local_header.file_name_length as usize + local_header.extra_field_length as usize];
+        source.read_exact(&mut file_name_and_extra_fields).map_err(|err| truncated_at(err, 30))?;
+        let (file_name, extra_fields) = file_name_and_extra_fields.split_at(local_header.file_name_length as usize);
+
+        // zip64 extra field in local file header always contains both sizes
+        let zip64_extra_field = find_extra_field(extra_fields, Zip64ExtraField::HEADER_ID)
+            .and_then(|bytes| Zip64ExtraField::select_from_bytes(bytes, &[
+                Zip64ExtraFieldSelect::UncompressedSize,
+                Zip64ExtraFieldSelect::CompressedSize,
+            ]));
+        let mut uncompressed_size = local_header.uncompressed_size as u64;
+        let mut compressed_size = local_header.compressed_size as u64;
+        if let Some(z64) = zip64_extra_field.as_ref() {
+            if local_header.uncompressed_size == u32::MAX { uncompressed_size = z64.uncompressed_size; }
+            if local_header.compressed_size == u32::MAX { compressed_size = z64.compressed_size; }
+        }
+
+        let file_name = decode_file_name(file_name, local_header.general_purpose_bit_flag, extra_fields);
+        let last_modified = DosDateTime { time: local_header.last_modified_file_time, date: local_header.last_modified_file_date };
+        let modified = extended_modification_time(extra_fields);
+        let ntfs_times = ntfs_times(extra_fields);
+        let unix_owner = unix_owner(extra_fields);
+        let extra_fields = ExtraField::parse_all(extra_fields);
+
+        Ok(if has_data_descriptor {
+            Self {
+                // deflate stream ends by itself, data descriptor is read from what is left
+                decoder: Decoder::new(compression_method, source.take(u64::MAX)),
+                file_name,
+                file_comment: String::new(),
+                archive_comment: String::new(),
+                crc_32: None,
+                compressed_size: None,
+                uncompressed_size: None,
+                last_modified,
+                modified,
+                ntfs_times,
+                unix_permissions: None,
+                unix_owner,
+                extra_fields,
+                data_descriptor: if zip64_extra_field.is_some() {
+                    DataDescriptorKind::Zip64
+                } else {
+                    DataDescriptorKind::Normal
+                },
+                verify: true,
+                running_crc_32: 0,
+            }
+        } else {
+            Self {
+                decoder: Decoder::new(compression_method, source.take(compressed_size)),
+                file_name,
+                file_comment: String::new(),
+                archive_comment: String::new(),
+                crc_32: Some(local_header.crc_32),
+                compressed_size: Some(compressed_size),
+                uncompressed_size: Some(uncompressed_size),
+                last_modified,
+                modified,
+                ntfs_times,
+                unix_permissions: None,
+                unix_owner,
+                extra_fields,
+                data_descriptor: DataDescriptorKind::None,
+                verify: true,
+                running_crc_32: 0,
+            }
         })
     }
+
+    /// Name of the entry, decoded as UTF-8 if flagged, otherwise taken from Unicode path extra field
+    /// if it matches, or decoded as code page 437.
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    /// Crc 32 of the entry, `None` if it is in a data descriptor which has not been reached yet.
+    pub fn crc_32(&self) -> Option<u32> {
+        self.crc_32
+    }
+
+    /// Compressed size of the entry, `None` if it is in a data descriptor which has not been reached yet.
+    pub fn compressed_size(&self) -> Option<u64> {
+        self.compressed_size
+    }
+
+    /// Uncompressed size of the entry, `None` if it is in a data descriptor which has not been reached yet.
+    pub fn uncompressed_size(&self) -> Option<u64> {
+        self.uncompressed_size
+    }
+
+    /// Last modification time as MS-DOS date and time.
+    pub fn last_modified(&self) -> DosDateTime {
+        self.last_modified
+    }
+
+    /// Last modification time from NTFS or extended timestamp extra field, `None` if both are missing.
+    ///
+    /// MS-DOS date and time is not used since its time zone is unknown, see `last_modified` for it.
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.ntfs_times.map(|times| times.modified).or(self.modified)
+    }
+
+    /// Times in NTFS extra field, if any.
+    pub fn ntfs_times(&self) -> Option<NtfsTimes> {
+        self.ntfs_times
+    }
+
+    /// Unix mode of the entry (file type included) if the archive is made on Unix,
+    /// always `None` when reading without central directory.
+    pub fn unix_permissions(&self) -> Option<u32> {
+        self.unix_permissions
+    }
+
+    /// Unix uid and gid of the entry in Info-ZIP new Unix extra field, if any.
+    pub fn unix_owner(&self) -> Option<(u32, u32)> {
+        self.unix_owner
+    }
+
+    /// All extra fields in order, from central directory header,
+    /// or from local file header when reading without central directory.
+    pub fn extra_fields(&self) -> &[ExtraField] {
+        &self.extra_fields
+    }
+
+    /// Comment of the entry, always empty when reading without central directory.
+    pub fn file_comment(&self) -> &str {
+        &self.file_comment
+    }
+
+    /// Comment of the archive, always empty when reading without central directory.
+    pub fn archive_comment(&self) -> &str {
+        &self.archive_comment
+    }
+
+    pub fn compression_method(&self) -> CompressionMethod {
+        self.decoder.compression_method()
+    }
+
+    /// Everything known about the entry so far, see the getters of `Reader`.
+    pub fn entry(&self) -> EntryInfo {
+        EntryInfo {
+            file_name: self.file_name.clone(),
+            file_comment: self.file_comment.clone(),
+            compression_method: self.compression_method(),
+            crc_32: self.crc_32,
+            compressed_size: self.compressed_size,
+            uncompressed_size: self.uncompressed_size,
+            last_modified: self.last_modified,
+            modified: self.modified(),
+            ntfs_times: self.ntfs_times,
+            unix_permissions: self.unix_permissions,
+            unix_owner: self.unix_owner,
+            extra_fields: self.extra_fields.clone(),
+        }
+    }
+
+    /// Whether to check crc 32 and sizes of the entry when reaching its end, default is `true`.
+    ///
+    /// Turning it off skips the crc computation, only do it when the archive is trusted.
+    pub fn set_verify(&mut self, verify: bool) {
+        self.verify = verify;
+    }
+
+    /// Give back the underlying source, its position is unspecified.
+    pub fn into_inner(self) -> R {
+        self.decoder.into_inner().into_inner().into_inner()
+    }
+
+    fn read_data_descriptor(&mut self) -> Result<(), ZipError> {
+        let zip64 = match self.data_descriptor {
+            DataDescriptorKind::None => return Ok(()),
+            DataDescriptorKind::Normal => false,
+            DataDescriptorKind::Zip64 => true,
+        };
+        let offset = self.decoder.total_in(); // relative to the start of compressed data
+        let data_descriptor = DataDescriptor::read_from(self.decoder.get_mut(), offset, zip64)?;
+        self.crc_32 = Some(data_descriptor.crc_32);
+        self.compressed_size = Some(data_descriptor.compressed_size);
+        self.uncompressed_size = Some(data_descriptor.uncompressed_size);
+        self.data_descriptor = DataDescriptorKind::None;
+        Ok(())
+    }
+
+    fn verify_entry(&self) -> Result<(), ZipError> {
+        if let Some(expected) = self.crc_32 && expected != self.running_crc_32 {
+            return Err(ZipError::ChecksumMismatch { expected, found: self.running_crc_32 });
+        }
+        if let Some(expected) = self.uncompressed_size && expected != self.decoder.total_out() {
+            return Err(ZipError::UncompressedSizeMismatch { expected, found: self.decoder.total_out() });
+        }
+        if let Some(expected) = self.compressed_size && expected != self.decoder.total_in() {
+            return Err(ZipError::CompressedSizeMismatch { expected, found: self.decoder.total_in() });
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> io::Read for Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.decoder.read(buf)?;
+        if len == 0 && !buf.is_empty() {
+            self.read_data_descriptor()?;
+            if self.verify {
+                self.verify_entry()?;
+            }
+        } else if self.verify {
+            self.running_crc_32 = crc32::run(self.running_crc_32, &buf[..len]);
+        }
+        Ok(len)
+    }
+}
+
+/// Metadata of the entry in a `Reader`,
+/// values in a data descriptor that has not been reached yet are `None`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntryInfo {
+    pub file_name: String,
+    pub file_comment: String,
+    pub compression_method: CompressionMethod,
+    pub crc_32: Option<u32>,
+    pub compressed_size: Option<u64>,
+    pub uncompressed_size: Option<u64>,
+    pub last_modified: DosDateTime,
+    /// Modification time from extra fields, see `Reader::modified`
+    pub modified: Option<SystemTime>,
+    pub ntfs_times: Option<NtfsTimes>,
+    pub unix_permissions: Option<u32>,
+    pub unix_owner: Option<(u32, u32)>,
+    pub extra_fields: Vec<ExtraField>,
+}
+
+
+enum Decoder<R: Read> {
+    Stored { source: BufReader<io::Take<R>>, total: u64 },
+    Deflate(DeflateDecoder<BufReader<io::Take<R>>>),
+    Readahead(Box<Readahead<R>>),
 }
+impl<R: Read> Decoder<R> {
+    fn new(compression_method: CompressionMethod, source: io::Take<R>) -> Self {
+        let source = BufReader::new(source);
+        match compression_method {
+            CompressionMethod::Stored => Self::Stored { source, total: 0 },
+            CompressionMethod::Deflate => Self::Deflate(DeflateDecoder::new(source)),
+        }
+    }
+
+    fn compression_method(&self) -> CompressionMethod {
+        match self {
+            Self::Stored { .. } => CompressionMethod::Stored,
+            Self::Deflate(_) => CompressionMethod::Deflate,
+            Self::Readahead(readahead) => readahead.compression_method(),
+        }
+    }
+
+    fn total_in(&self) -> u64 {
+        match self {
+            Self::Stored { total, .. } => *total,
+            Self::Deflate(decoder) => decoder.total_in(),
+            Self::Readahead(readahead) => readahead.decoder().total_in(),
+        }
+    }
+
+    fn total_out(&self) -> u64 {
+        match self {
+            Self::Stored { total, .. } => *total,
+            Self::Deflate(decoder) => decoder.total_out(),
+            Self::Readahead(readahead) => readahead.decoder().total_out(),
+        }
+    }
+
+    fn get_mut(&mut self) -> &mut BufReader<io::Take<R>> {
+        match self {
+            Self::Stored { source, .. } => source,
+            Self::Deflate(decoder) => decoder.get_mut(),
+            Self::Readahead(readahead) => readahead.decoder_mut().get_mut(),
+        }
+    }
 
-impl io::Read for Reader {
+    fn into_inner(self) -> BufReader<io::Take<R>> {
+        match self {
+            Self::Stored { source, .. } => source,
+            Self::Deflate(decoder) => decoder.into_inner(),
+            Self::Readahead(readahead) => readahead.into_inner().into_inner(),
+        }
+    }
+}
+impl<R: Read> io::Read for Decoder<R> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.deflate_reader.read(buf)
+        match self {
+            Self::Stored { source, total } => {
+                let len = source.read(buf)?;
+                *total += len as u64;
+                Ok(len)
+            },
+            Self::Deflate(decoder) => decoder.read(buf),
+            Self::Readahead(readahead) => readahead.read(buf),
+        }
+    }
+}
+
+enum DataDescriptorKind {
+    /// No data descriptor, or it has been read already
+    None,
+    /// Sizes are 4 bytes
+    Normal,
+    /// Sizes are 8 bytes, used when local file header has zip64 extra field
+    Zip64,
+}
+
+/// Tracks the absolute position of a sink while writing into it.
+struct CountingWriter<W: Write> {
+    inner: W,
+    count: u64,
+}
+impl<W: Write> io::Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.inner.write(buf)?;
+        self.count += len as u64;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
     }
 }
 
+fn check_file_name(file_name: &[u8]) -> Result<(), ZipError> {
+    let reason = if file_name.contains(&b'\\') {
+        "backslash is not a separator in zip, use forward slash"
+    } else if file_name.starts_with(b"/") {
+        "absolute path"
+    } else if file_name.len() >= 2 && file_name[0].is_ascii_alphabetic() && file_name[1] == b':' {
+        "drive letter"
+    } else if file_name.ends_with(b"/") {
+        "trailing slash makes a directory entry"
+    } else if file_name.split(|&b| b == b'/').any(|component| component == b"..") {
+        "parent directory component"
+    } else if file_name.split(|&b| b == b'/').any(|component| component.is_empty() || component == b".") {
+        "empty or current directory component"
+    } else {
+        return Ok(());
+    };
+    Err(ZipError::InvalidFileName { name: String::from_utf8_lossy(file_name).into_owned(), reason })
+}
+
+fn decode_file_name(file_name: &[u8], general_purpose_bit_flag: u16, extra_fields: &[u8]) -> String {
+    if general_purpose_bit_flag & GENERAL_PURPOSE_BIT_FLAG_UTF8 != 0 {
+        return String::from_utf8_lossy(file_name).into_owned();
+    }
+    let unicode_path = find_extra_field(extra_fields, UnicodePathExtraField::HEADER_ID)
+        .and_then(UnicodePathExtraField::from_bytes)
+        .filter(|field| field.version == UnicodePathExtraField::VERSION && field.name_crc_32 == crc32::run(0, file_name))
+        .and_then(|field| String::from_utf8(field.unicode_name).ok());
+    unicode_path.unwrap_or_else(|| cp437::decode(file_name))
+}
+
+/// Like `decode_file_name`, with Unicode comment extra field.
+fn decode_file_comment(file_comment: &[u8], general_purpose_bit_flag: u16, extra_fields: &[u8]) -> String {
+    if general_purpose_bit_flag & GENERAL_PURPOSE_BIT_FLAG_UTF8 != 0 {
+        return String::from_utf8_lossy(file_comment).into_owned();
+    }
+    let unicode_comment = find_extra_field(extra_fields, UnicodeCommentExtraField::HEADER_ID)
+        .and_then(UnicodeCommentExtraField::from_bytes)
+        .filter(|field| field.version == UnicodeCommentExtraField::VERSION && field.comment_crc_32 == crc32::run(0, file_comment))
+        .and_then(|field| String::from_utf8(field.unicode_comment).ok());
+    unicode_comment.unwrap_or_else(|| cp437::decode(file_comment))
+}
+
+fn extended_modification_time(extra_fields: &[u8]) -> Option<SystemTime> {
+    find_extra_field(extra_fields, ExtendedTimestampExtraField::HEADER_ID)
+        .and_then(ExtendedTimestampExtraField::from_bytes)
+        .filter(|field| field.flags & ExtendedTimestampExtraField::FLAG_MODIFICATION_TIME != 0)
+        .map(|field| time::from_unix_time(field.modification_time))
+}
+
+fn ntfs_times(extra_fields: &[u8]) -> Option<NtfsTimes> {
+    find_extra_field(extra_fields, NtfsExtraField::HEADER_ID)
+        .and_then(NtfsExtraField::from_bytes)
+        .map(|field| NtfsTimes {
+            modified: time::from_filetime(field.modification_time),
+            accessed: time::from_filetime(field.access_time),
+            created: time::from_filetime(field.creation_time),
+        })
+}
+
+fn unix_owner(extra_fields: &[u8]) -> Option<(u32, u32)> {
+    find_extra_field(extra_fields, UnixExtraField::HEADER_ID)
+        .and_then(UnixExtraField::from_bytes)
+        .map(|field| (field.uid, field.gid))
+}
+
+/// Position of end of central directory record, the record and the archive comment after it.
+fn find_end_of_central_directory(
+    source: &mut (impl io::Read + io::Seek),
+) -> Result<(u64, EndOfCentralDirectoryRecord, Vec<u8>), ZipError> {
+    // the record is followed by a comment up to `u16::MAX` bytes
+    let file_size = source.seek(SeekFrom::End(0))?;
+    let tail_size = file_size.min(22 + u16::MAX as u64);
+    source.seek(SeekFrom::Start(file_size - tail_size))?;
+    let mut tail = vec![0u8; tail_size as usize];
+    source.read_exact(&mut tail)?;
+    let eocd_pos_in_tail = (0..tail.len().saturating_sub(21)).rev()
+        .find(|&i| {
+            let comment_length = u16::from_le_bytes([tail[i + 20], tail[i + 21]]) as usize;
+            tail[i..i + 4] == EndOfCentralDirectoryRecord::SIGNATURE && i + 22 + comment_length <= tail.len()
+        })
+        .ok_or(ZipError::EndOfCentralDirectoryNotFound)?;
+    let eocd = EndOfCentralDirectoryRecord::from_bytes(
+        tail[eocd_pos_in_tail..eocd_pos_in_tail + 22].try_into().unwrap()
+    );
+    let comment_pos = eocd_pos_in_tail + 22;
+    let comment = tail[comment_pos..comment_pos + eocd.zip_file_comment_length as usize].to_vec();
+    Ok((file_size - tail_size + eocd_pos_in_tail as u64, eocd, comment))
+}
+
+fn read_array_at<const N: usize>(r: &mut (impl io::Read + io::Seek), offset: u64) -> Result<[u8; N], ZipError> {
+    r.seek(SeekFrom::Start(offset))?;
+    read_array(r).map_err(|err| truncated_at(err, offset))
+}
+
 
 #[cfg(test)]
 mod tests {
-    use std::io::Read;
-
     use super::*;
+    use std::io::ErrorKind;
 
     const ZERO_ZIP: &str = "test.zero.zip";
     const SMALL_ZIP: &str = "test.small.txt.zip";
     const SMALL_STR: &str = "The quick brown fox jumps over the lazy dog";
+    /// "hello hello hello world\n" zipped by python `zipfile` with `force_zip64` and an archive comment
+    const PYTHON_ZIP: [u8; 166] = [
+        0x50, 0x4b, 0x03, 0x04, 0x2d, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x21, 0x00, 0x88, 0xd9,
+        0x5b, 0xe0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x09, 0x00, 0x14, 0x00, 0x68, 0x65,
+        0x6c, 0x6c, 0x6f, 0x2e, 0x74, 0x78, 0x74, 0x01, 0x00, 0x10, 0x00, 0x18, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xcb, 0x48, 0xcd, 0xc9, 0xc9,
+        0x57, 0xc8, 0x40, 0x22, 0xcb, 0xf3, 0x8b, 0x72, 0x52, 0xb8, 0x00, 0x50, 0x4b, 0x01, 0x02, 0x2d,
+        0x03, 0x2d, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x21, 0x00, 0x88, 0xd9, 0x5b, 0xe0, 0x10,
+        0x00, 0x00, 0x00, 0x18, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x80, 0x01, 0x00, 0x00, 0x00, 0x00, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x2e, 0x74,
+        0x78, 0x74, 0x50, 0x4b, 0x05, 0x06, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x37, 0x00,
+        0x00, 0x00, 0x4b, 0x00, 0x00, 0x00, 0x0e, 0x00, 0x6d, 0x61, 0x64, 0x65, 0x20, 0x62, 0x79, 0x20,
+        0x70, 0x79, 0x74, 0x68, 0x6f, 0x6e,
+    ];
 
     struct PcgXshRr { // https://www.pcg-random.org
         state: u64,
@@ -307,13 +1434,655 @@ mod tests {
         w.finish().unwrap();
     }
 
+    #[test]
+    fn cursor_write() {
+        let prefix = b"#!/bin/sh\n";
+        let mut sink = io::Cursor::new(prefix.to_vec());
+        sink.seek(SeekFrom::End(0)).unwrap();
+        let mut w = Writer::new(sink, "small.txt").unwrap();
+        w.write_all(SMALL_STR.as_bytes()).unwrap();
+        let bytes = w.finish().unwrap().into_inner();
+        assert!(bytes.starts_with(prefix));
+        assert!(bytes[prefix.len()..].starts_with(&LocalFileHeader::SIGNATURE));
+    }
+
+    #[test]
+    fn streaming_write() {
+        let mut w = Writer::new_streaming(Vec::new(), "small.txt").unwrap();
+        w.write_all(SMALL_STR.as_bytes()).unwrap();
+        let bytes = w.finish().unwrap();
+        let flag = u16::from_le_bytes([bytes[6], bytes[7]]);
+        assert!(flag & GENERAL_PURPOSE_BIT_FLAG_DATA_DESCRIPTOR != 0);
+        let cd_pos = bytes.windows(4).position(|s| s == CentralDirectoryHeader::SIGNATURE).unwrap();
+        let dd = &bytes[cd_pos - 24..cd_pos];
+        assert!(dd[0..4] == DataDescriptor::SIGNATURE);
+        assert!(dd[4..8] == crc32::run(0, SMALL_STR.as_bytes()).to_le_bytes());
+        assert!(dd[16..24] == (SMALL_STR.len() as u64).to_le_bytes());
+    }
+
+    #[test]
+    fn options_write() {
+        // 2026-10-17 12:34:56 UTC
+        let modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1792240496);
+        let mut w = WriterOptions::new()
+            .compression(Compression::fast())
+            .entry_name("run.sh")
+            .modified(modified)
+            .comment("job 42")
+            .archive_comment("made by job 42")
+            .unix_permissions(0o755)
+            .unix_owner(1000, 100)
+            .build(io::Cursor::new(Vec::new()))
+            .unwrap();
+        w.write_all(SMALL_STR.as_bytes()).unwrap();
+        let bytes = w.finish().unwrap().into_inner();
+
+        let cd_pos = bytes.windows(4).position(|s| s == CentralDirectoryHeader::SIGNATURE).unwrap();
+        let cd_header = CentralDirectoryHeader::from_bytes(bytes[cd_pos..cd_pos + 46].try_into().unwrap());
+        assert!(cd_header.version_made_by == VERSION_MADE_BY_UNIX | TARGET_ZIP_VERSION);
+        assert!(cd_header.external_file_attributes >> 16 == 0o100755);
+        assert!(cd_header.last_modified_file_date == (46 << 9) | (10 << 5) | 17);
+        assert!(cd_header.last_modified_file_time == (12 << 11) | (34 << 5) | (56 / 2));
+        let r = Reader::new(io::Cursor::new(bytes.as_slice())).unwrap();
+        assert!(r.modified() == Some(modified));
+        assert!(r.last_modified() == DosDateTime::new(2026, 10, 17, 12, 34, 56).unwrap());
+        assert!(r.last_modified().to_system_time() == Some(modified));
+        assert!(r.unix_permissions() == Some(0o100755));
+        assert!(r.unix_owner() == Some((1000, 100)));
+        let entry = r.entry();
+        assert!(entry.file_name == "run.sh" && entry.file_comment == "job 42");
+        assert!(r.archive_comment() == "made by job 42" && bytes.ends_with(b"made by job 42"));
+        assert!(entry.compression_method == CompressionMethod::Deflate);
+        assert!(entry.crc_32 == Some(crc32::run(0, SMALL_STR.as_bytes())));
+        assert!(entry.uncompressed_size == Some(SMALL_STR.len() as u64));
+        assert!(entry.compressed_size == Some(cd_header.compressed_size as u64));
+        assert!(entry.modified == Some(modified) && entry.unix_permissions == Some(0o100755));
+        let comment_pos = cd_pos + 46 + cd_header.file_name_length as usize + cd_header.extra_field_length as usize;
+        assert!(bytes[comment_pos..comment_pos + cd_header.file_comment_length as usize] == *b"job 42");
+    }
+
+    #[test]
+    fn stored_read() {
+        let options = WriterOptions::new().entry_name("small.txt").compression_method(CompressionMethod::Stored);
+        let mut w = options.clone().build(io::Cursor::new(Vec::new())).unwrap();
+        w.write_all(SMALL_STR.as_bytes()).unwrap();
+        let bytes = w.finish().unwrap().into_inner();
+        assert!(bytes.windows(SMALL_STR.len()).any(|s| s == SMALL_STR.as_bytes()));
+        for mut r in [
+            Reader::new(io::Cursor::new(bytes.as_slice())).unwrap(),
+            Reader::new_streaming(io::Cursor::new(bytes.as_slice())).unwrap(),
+        ] {
+            let mut s = String::new();
+            r.read_to_string(&mut s).unwrap();
+            assert!(s.eq(SMALL_STR));
+        }
+
+        let mut w = options.build_streaming(Vec::new()).unwrap();
+        w.write_all(SMALL_STR.as_bytes()).unwrap();
+        let bytes = w.finish().unwrap();
+        let mut r = Reader::new(io::Cursor::new(bytes.as_slice())).unwrap();
+        let mut s = String::new();
+        r.read_to_string(&mut s).unwrap();
+        assert!(s.eq(SMALL_STR));
+        let err = Reader::new_streaming(bytes.as_slice()).err().unwrap();
+        assert!(matches!(err, ZipError::StoredWithDataDescriptor));
+    }
+
+    #[test]
+    fn fallback_write() {
+        let mut rng = PcgXshRr::new(0);
+        let random: Vec<u8> = (0..64 * 1024).flat_map(|_| rng.get().to_ne_bytes()).collect();
+        let text = SMALL_STR.repeat(4096);
+        for (data, method) in [
+            (random.as_slice(), COMPRESSION_METHOD_STORED),
+            (text.as_bytes(), COMPRESSION_METHOD_DEFLATE),
+        ] {
+            let options = WriterOptions::new().entry_name("data").fallback_to_stored(64 * 1024);
+            let mut w = options.build_streaming(Vec::new()).unwrap();
+            w.write_all(data).unwrap();
+            let bytes = w.finish().unwrap();
+            assert!(u16::from_le_bytes([bytes[8], bytes[9]]) == method);
+            let cd_pos = bytes.windows(4).position(|s| s == CentralDirectoryHeader::SIGNATURE).unwrap();
+            assert!(u16::from_le_bytes([bytes[cd_pos + 10], bytes[cd_pos + 11]]) == method);
+
+            let mut r = Reader::new(io::Cursor::new(bytes.as_slice())).unwrap();
+            let mut buf = Vec::new();
+            r.read_to_end(&mut buf).unwrap();
+            assert!(buf == data);
+            match Reader::new_streaming(bytes.as_slice()) {
+                Ok(mut r) => {
+                    assert!(method == COMPRESSION_METHOD_DEFLATE);
+                    let mut buf = Vec::new();
+                    r.read_to_end(&mut buf).unwrap();
+                    assert!(buf == data);
+                },
+                Err(err) => assert!(method == COMPRESSION_METHOD_STORED && matches!(err, ZipError::StoredWithDataDescriptor)),
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_write() {
+        let mut rng = PcgXshRr::new(7);
+        let mut data = SMALL_STR.repeat(8192).into_bytes();
+        data.extend((0..32 * 1024).flat_map(|_| rng.get().to_ne_bytes()));
+        data.extend_from_within(..300 * 1024);
+        let mut w = WriterOptions::new().entry_name("data").build(io::Cursor::new(Vec::new())).unwrap();
+        w.write_all(&data).unwrap();
+        let single_threaded_len = w.finish().unwrap().into_inner().len();
+        for options in [
+            WriterOptions::new().entry_name("data").threads(4),
+            WriterOptions::new().entry_name("data").threads(3).fallback_to_stored(100 * 1024),
+        ] {
+            let mut w = options.clone().build(io::Cursor::new(Vec::new())).unwrap();
+            for chunk in data.chunks(50_000) {
+                w.write_all(chunk).unwrap();
+            }
+            let bytes = w.finish().unwrap().into_inner();
+            assert!(u16::from_le_bytes([bytes[8], bytes[9]]) == COMPRESSION_METHOD_DEFLATE);
+            assert!(bytes.len() < single_threaded_len + single_threaded_len / 50);
+            assert!(validate(io::Cursor::new(bytes.as_slice())).unwrap().is_empty());
+            let mut r = Reader::new(io::Cursor::new(bytes.as_slice())).unwrap();
+            let mut buf = Vec::new();
+            r.read_to_end(&mut buf).unwrap();
+            assert!(buf == data && r.crc_32() == Some(Crc32::checksum(&data)));
+
+            let w = options.build_streaming(Vec::new()).unwrap();
+            let bytes = w.finish().unwrap();
+            let mut r = Reader::new(io::Cursor::new(bytes)).unwrap();
+            let mut buf = Vec::new();
+            r.read_to_end(&mut buf).unwrap();
+            assert!(buf.is_empty());
+        }
+    }
+
+    #[test]
+    fn pipelined_write() {
+        let data = SMALL_STR.repeat(20_000);
+        let w = WriterOptions::new().entry_name("data").threads(2).build(io::Cursor::new(Vec::new())).unwrap();
+        let mut w = w.pipelined(4);
+        for line in data.as_bytes().chunks(SMALL_STR.len()) {
+            w.write_all(line).unwrap();
+        }
+        w.flush().unwrap();
+        let bytes = w.finish().unwrap().into_inner();
+        let mut r = Reader::new(io::Cursor::new(bytes)).unwrap();
+        let mut s = String::new();
+        r.read_to_string(&mut s).unwrap();
+        assert!(s == data);
+
+        /// Accepts `0` more bytes before failing
+        struct FullSink(usize);
+        impl Write for FullSink {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.0 == 0 {
+                    return Err(ErrorKind::StorageFull.into());
+                }
+                let len = buf.len().min(self.0);
+                self.0 -= len;
+                Ok(len)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        let options = WriterOptions::new().entry_name("data").compression_method(CompressionMethod::Stored);
+        let mut w = options.clone().build_streaming(FullSink(100_000)).unwrap().pipelined(1);
+        let err = (0..100).find_map(|_| w.write_all(data.as_bytes()).err()).unwrap();
+        assert!(err.kind() == ErrorKind::StorageFull);
+        assert!(w.write_all(b"more").is_err() && w.finish().is_err());
+
+        let mut w = options.clone().build_streaming(FullSink(90_050)).unwrap().pipelined(1);
+        w.write_all(&data.as_bytes()[..90_000]).unwrap();
+        assert!(w.finish().err().unwrap().kind() == ErrorKind::StorageFull);
+
+        // flush waits for the writer thread
+        let mut w = options.build_streaming(FullSink(90_050)).unwrap().pipelined(4);
+        assert!(w.write(data.as_bytes()).unwrap() == 64 * 1024);
+        w.write_all(&data.as_bytes()[..30_000]).unwrap();
+        assert!(w.flush().unwrap_err().kind() == ErrorKind::StorageFull);
+        assert!(w.finish().is_err());
+    }
+
+    #[test]
+    fn readahead_read() {
+        let mut rng = PcgXshRr::new(3);
+        let mut data = SMALL_STR.repeat(10_000).into_bytes();
+        data.extend((0..64 * 1024).flat_map(|_| rng.get().to_ne_bytes()));
+        for method in [CompressionMethod::Deflate, CompressionMethod::Stored] {
+            let options = WriterOptions::new().entry_name("data").compression_method(method);
+            let mut w = options.clone().build(io::Cursor::new(Vec::new())).unwrap();
+            w.write_all(&data).unwrap();
+            let bytes = w.finish().unwrap().into_inner();
+            let mut r = Reader::new(io::Cursor::new(bytes.clone())).unwrap().readahead(2);
+            let mut buf = Vec::new();
+            let mut chunk = [0; 1000];
+            loop {
+                let len = r.read(&mut chunk).unwrap();
+                if len == 0 {
+                    break;
+                }
+                buf.extend_from_slice(&chunk[..len]);
+            }
+            assert!(buf == data && r.compression_method() == method);
+
+            // crc is checked at the end as without readahead
+            let mut corrupted = bytes.clone();
+            corrupted[14] ^= 1;
+            let cd_pos = corrupted.windows(4).rposition(|s| s == CentralDirectoryHeader::SIGNATURE).unwrap();
+            corrupted[cd_pos + 16] ^= 1;
+            let mut r = Reader::new(io::Cursor::new(corrupted)).unwrap().readahead(3);
+            let err = r.read_to_end(&mut Vec::new()).unwrap_err();
+            assert!(err.kind() == ErrorKind::InvalidData);
+
+            // stops the background thread in the middle of the entry
+            let mut r = Reader::new(io::Cursor::new(bytes)).unwrap().readahead(1);
+            r.read_exact(&mut chunk).unwrap();
+            assert!(chunk == data[..1000]);
+            r.into_inner();
+        }
+
+        let mut w = WriterOptions::new().entry_name("data").build_streaming(Vec::new()).unwrap();
+        w.write_all(&data).unwrap();
+        let bytes = w.finish().unwrap();
+        let mut r = Reader::new_streaming(io::Cursor::new(bytes)).unwrap().readahead(4);
+        assert!(r.crc_32().is_none());
+        let mut buf = Vec::new();
+        r.read_to_end(&mut buf).unwrap();
+        assert!(buf == data && r.crc_32() == Some(Crc32::checksum(&data)));
+    }
+
+    #[test]
+    fn dos_date_time() {
+        let t = DosDateTime::new(2024, 2, 29, 23, 59, 59).unwrap();
+        assert!((t.year(), t.month(), t.day(), t.hour(), t.minute(), t.second()) == (2024, 2, 29, 23, 59, 58));
+        assert!(DosDateTime::from_system_time(t.to_system_time().unwrap()) == t);
+        assert!(DosDateTime::new(2023, 2, 29, 0, 0, 0).is_none());
+        assert!(DosDateTime::from_system_time(std::time::UNIX_EPOCH) == DosDateTime::MIN);
+        assert!(DosDateTime { time: 0, date: 0 }.to_system_time().is_none());
+
+        // extended timestamp holds signed seconds
+        let epoch = std::time::UNIX_EPOCH;
+        let before_epoch = epoch - std::time::Duration::from_secs(86400 * 365);
+        let bytes = WriterOptions::new().entry_name("a").modified(before_epoch).build_streaming(Vec::new()).unwrap().finish().unwrap();
+        assert!(Reader::new(io::Cursor::new(bytes.as_slice())).unwrap().modified() == Some(before_epoch));
+        assert!(time::to_unix_time(epoch - std::time::Duration::from_millis(1500)) == Some(-2));
+        let field = ExtendedTimestampExtraField::from_bytes(&[0x55, 0x54, 5, 0, 1, 0xFF, 0xFF, 0xFF, 0xFF]).unwrap();
+        assert!(time::from_unix_time(field.modification_time) == epoch - std::time::Duration::from_secs(1));
+
+        // python `zipfile` writes local time and no extended timestamp
+        let r = Reader::new(io::Cursor::new(PYTHON_ZIP)).unwrap();
+        assert!(r.modified().is_none() && r.entry().modified.is_none());
+        assert!(r.last_modified().to_system_time().is_some());
+    }
+
+    #[test]
+    fn ntfs_times() {
+        let epoch = std::time::UNIX_EPOCH;
+        let times = NtfsTimes {
+            modified: epoch + std::time::Duration::new(1792240496, 123_456_700),
+            accessed: epoch + std::time::Duration::new(1792240497, 100),
+            created: epoch - std::time::Duration::new(86400, 0),
+        };
+        let mut w = WriterOptions::new()
+            .entry_name("small.txt")
+            .ntfs_times(times)
+            .build_streaming(Vec::new())
+            .unwrap();
+        w.write_all(SMALL_STR.as_bytes()).unwrap();
+        let bytes = w.finish().unwrap();
+        for r in [
+            Reader::new(io::Cursor::new(bytes.as_slice())).unwrap(),
+            Reader::new_streaming(io::Cursor::new(bytes.as_slice())).unwrap(),
+        ] {
+            assert!(r.ntfs_times() == Some(times));
+            assert!(r.modified() == Some(times.modified));
+        }
+    }
+
+    #[test]
+    fn file_names() {
+        let write = |name: &OsStr| {
+            let mut w = Writer::new_streaming(Vec::new(), name).unwrap();
+            w.write_all(SMALL_STR.as_bytes()).unwrap();
+            w.finish().unwrap()
+        };
+        let bytes = write(OsStr::new("déjà vu.txt"));
+        let header = LocalFileHeader::from_bytes(bytes[..30].try_into().unwrap());
+        assert!(header.general_purpose_bit_flag & GENERAL_PURPOSE_BIT_FLAG_UTF8 != 0);
+        assert!(bytes[30..30 + header.file_name_length as usize] == *"déjà vu.txt".as_bytes());
+        assert!(Reader::new(io::Cursor::new(bytes.as_slice())).unwrap().file_name() == "déjà vu.txt");
+        assert!(Reader::new_streaming(bytes.as_slice()).unwrap().file_name() == "déjà vu.txt");
+
+        assert!(cp437::encode("déjà vu €") == b"d\x82j\x85 vu _");
+        assert!(cp437::decode(b"d\x82j\x85 vu _") == "déjà vu _");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let mut bytes = write(OsStr::from_bytes(b"caf\xe9.txt"));
+            let header = LocalFileHeader::from_bytes(bytes[..30].try_into().unwrap());
+            assert!(header.general_purpose_bit_flag & GENERAL_PURPOSE_BIT_FLAG_UTF8 == 0);
+            assert!(bytes[30..30 + header.file_name_length as usize] == *b"caf_.txt");
+            assert!(Reader::new(io::Cursor::new(bytes.as_slice())).unwrap().file_name() == "caf\u{FFFD}.txt");
+            assert!(Reader::new_streaming(bytes.as_slice()).unwrap().file_name() == "caf\u{FFFD}.txt");
+            // Unicode path extra field is ignored if its crc does not match the name
+            let extra_fields = &bytes[38..38 + header.extra_field_length as usize];
+            let field_pos = 38 + extra_fields.windows(2).position(|s| s == 0x7075u16.to_le_bytes()).unwrap();
+            bytes[field_pos + 5] ^= 1;
+            assert!(Reader::new_streaming(bytes.as_slice()).unwrap().file_name() == "caf_.txt");
+
+            // the lossy UTF-8 name is longer than the code page 437 one
+            for len in [65532, 65527] {
+                let mut name = vec![b'a'; len];
+                name.push(0xff);
+                let err = Writer::new_streaming(Vec::new(), OsStr::from_bytes(&name)).err().unwrap();
+                assert!(matches!(err, ZipError::ExtraFieldsTooLong { .. }));
+            }
+
+            // so is the comment, in Unicode comment extra field of central directory header
+            let options = WriterOptions::new().entry_name(OsStr::from_bytes(b"caf\xe9.txt"));
+            let mut w = options.clone().comment("été – job 42").build_streaming(Vec::new()).unwrap();
+            w.write_all(SMALL_STR.as_bytes()).unwrap();
+            let bytes = w.finish().unwrap();
+            let r = Reader::new(io::Cursor::new(bytes.as_slice())).unwrap();
+            assert!(r.file_comment() == "été – job 42");
+            let cd_pos = bytes.windows(4).position(|s| s == CentralDirectoryHeader::SIGNATURE).unwrap();
+            let cd_header = CentralDirectoryHeader::from_bytes(bytes[cd_pos..cd_pos + 46].try_into().unwrap());
+            let comment_pos = cd_pos + 46 + cd_header.file_name_length as usize + cd_header.extra_field_length as usize;
+            assert!(bytes[comment_pos..comment_pos + cd_header.file_comment_length as usize] == *b"\x82t\x82 _ job 42");
+            let local_header = LocalFileHeader::from_bytes(bytes[..30].try_into().unwrap());
+            let local_extra_fields = ExtraField::parse_all(&bytes[38..38 + local_header.extra_field_length as usize]);
+            assert!(local_extra_fields.iter().all(|field| field.header_id() != UnicodeCommentExtraField::HEADER_ID));
+            // an ASCII comment needs no field
+            let mut w = options.clone().comment("job 42").build_streaming(Vec::new()).unwrap();
+            w.write_all(SMALL_STR.as_bytes()).unwrap();
+            let bytes = w.finish().unwrap();
+            assert!(!bytes.windows(2).any(|s| s == UnicodeCommentExtraField::HEADER_ID.to_le_bytes()));
+            assert!(Reader::new(io::Cursor::new(bytes.as_slice())).unwrap().file_comment() == "job 42");
+            // the UTF-8 comment can be twice as long as the code page 437 one
+            let err = options.comment("é".repeat(32767)).build_streaming(Vec::new()).err().unwrap();
+            assert!(matches!(err, ZipError::ExtraFieldsTooLong { .. }));
+        }
+    }
+
+    #[test]
+    fn entry_names() {
+        let path = Path::new("test.entry_names.zip");
+        let mut w = WriterOptions::new().entry_name("data/2026/report.csv").create(path).unwrap();
+        w.write_all(SMALL_STR.as_bytes()).unwrap();
+        w.finish().unwrap();
+        assert!(Reader::open(path).unwrap().file_name() == "data/2026/report.csv");
+
+        for name in [
+            "data\\report.csv", "/etc/passwd", "C:report.csv", "data/../../report.csv", "..",
+            "dir/", "a//b", "./a", "a/./b", ".",
+        ] {
+            let err = Writer::new_streaming(Vec::new(), name).err().unwrap();
+            assert!(matches!(err, ZipError::InvalidFileName { .. }));
+            assert!(err.kind() == ErrorKind::InvalidFilename);
+        }
+    }
+
+    #[test]
+    fn extra_fields() {
+        let tag = ExtraField::Unknown { id: 0xCAFE, data: b"job 42".to_vec() };
+        let options = WriterOptions::new().entry_name("small.txt").unix_owner(1000, 100).extra_field(tag.clone());
+        let mut w = options.clone().build(io::Cursor::new(Vec::new())).unwrap();
+        w.write_all(SMALL_STR.as_bytes()).unwrap();
+        let bytes = w.finish().unwrap().into_inner();
+        let r = Reader::new(io::Cursor::new(bytes.as_slice())).unwrap();
+        let ids = r.extra_fields().iter().map(ExtraField::header_id).collect::<Vec<_>>();
+        assert!(ids == [0x5455, 0x7875, 0xCAFE]);
+        assert!(r.extra_fields()[1] == ExtraField::Unix(UnixExtraField {
+            header_id: 0x7875, data_size: 11, version: 1, uid: 1000, gid: 100,
+        }));
+        assert!(r.extra_fields()[2] == tag);
+
+        let mut w = options.build_streaming(Vec::new()).unwrap();
+        w.write_all(SMALL_STR.as_bytes()).unwrap();
+        let bytes = w.finish().unwrap();
+        let r = Reader::new_streaming(bytes.as_slice()).unwrap();
+        let ids = r.extra_fields().iter().map(ExtraField::header_id).collect::<Vec<_>>();
+        assert!(ids == [0x0001, 0x5455, 0x7875, 0xCAFE]);
+        let ExtraField::Zip64(zip64) = &r.extra_fields()[0] else { panic!() };
+        assert!(zip64.data_size == 16);
+        // the selected values must fill the field exactly
+        let sizes = [Zip64ExtraFieldSelect::UncompressedSize, Zip64ExtraFieldSelect::CompressedSize];
+        let mut zip64_bytes = zip64.select_to_bytes(&sizes);
+        assert!(Zip64ExtraField::select_from_bytes(&zip64_bytes, &sizes).as_ref() == Some(zip64));
+        assert!(Zip64ExtraField::select_from_bytes(&zip64_bytes, &sizes[..1]).is_none());
+        assert!(Zip64ExtraField::select_from_bytes(&zip64_bytes[..12], &sizes).is_none());
+        zip64_bytes[0] = 2;
+        assert!(Zip64ExtraField::select_from_bytes(&zip64_bytes, &sizes).is_none());
+        assert!(ExtraField::from_bytes(&[1, 0, 4, 0, 1, 2, 3, 4]) == Some(ExtraField::Unknown { id: 1, data: vec![1, 2, 3, 4] }));
+        let block = r.extra_fields().iter().flat_map(|field| field.to_bytes(false)).collect::<Vec<_>>();
+        assert!(ExtraField::parse_all(&block) == r.extra_fields());
+        assert!(ExtraField::from_bytes(&[0xFE, 0xCA, 1, 0, 7]) == Some(ExtraField::Unknown { id: 0xCAFE, data: vec![7] }));
+        assert!(ExtraField::from_bytes(&[1]).is_none() && ExtraField::from_bytes(&[1, 0, 0]).is_none());
+
+        let too_long = ExtraField::Unknown { id: 0xCAFE, data: vec![0; u16::MAX as usize - 4] };
+        let err = WriterOptions::new().entry_name("a").extra_field(too_long).build_streaming(Vec::new()).err().unwrap();
+        assert!(matches!(err, ZipError::ExtraFieldsTooLong { .. }));
+        for too_long in [
+            ExtraField::Unknown { id: 0xCAFE, data: vec![0; u16::MAX as usize + 1] },
+            ExtraField::UnicodePath(UnicodePathExtraField {
+                header_id: 0x7075, data_size: 0, version: 1, name_crc_32: 0, unicode_name: vec![b'a'; u16::MAX as usize - 4],
+            }),
+        ] {
+            let err = WriterOptions::new().entry_name("a").extra_field(too_long).build_streaming(Vec::new()).err().unwrap();
+            assert!(matches!(err, ZipError::ExtraFieldsTooLong { .. }));
+        }
+
+        // replaces the field `Writer` would write
+        let owner = ExtraField::Unix(UnixExtraField { header_id: 0x7875, data_size: 11, version: 1, uid: 0, gid: 0 });
+        let options = WriterOptions::new().entry_name("a").unix_owner(1000, 100).extra_field(owner);
+        let bytes = options.build_streaming(Vec::new()).unwrap().finish().unwrap();
+        let r = Reader::new(io::Cursor::new(bytes.as_slice())).unwrap();
+        let ids = r.extra_fields().iter().map(ExtraField::header_id).collect::<Vec<_>>();
+        assert!(ids == [0x5455, 0x7875] && r.unix_owner() == Some((0, 0)));
+    }
+
+    #[test]
+    fn cursor_read() {
+        let mut w = Writer::new(io::Cursor::new(Vec::new()), "small.txt").unwrap();
+        w.write_all(SMALL_STR.as_bytes()).unwrap();
+        let sink = w.finish().unwrap();
+        let mut r = Reader::new(io::Cursor::new(sink.into_inner())).unwrap();
+        let mut s = String::new();
+        r.read_to_string(&mut s).unwrap();
+        assert!(s.eq(SMALL_STR));
+        assert!(!r.into_inner().into_inner().is_empty());
+    }
+
+    #[test]
+    fn foreign_read() {
+        let mut archive = b"prepended stub".to_vec();
+        archive.extend_from_slice(&PYTHON_ZIP);
+        let mut r = Reader::new(io::Cursor::new(archive)).unwrap();
+        assert!(r.archive_comment() == "made by python");
+        let mut s = String::new();
+        r.read_to_string(&mut s).unwrap();
+        assert!(s.eq("hello hello hello world\n"));
+    }
+
+    #[test]
+    fn streaming_read() {
+        let mut w = Writer::new_streaming(Vec::new(), "small.txt").unwrap();
+        w.write_all(SMALL_STR.as_bytes()).unwrap();
+        let bytes = w.finish().unwrap();
+        let mut r = Reader::new_streaming(bytes.as_slice()).unwrap();
+        assert!(r.crc_32().is_none());
+        let mut s = String::new();
+        r.read_to_string(&mut s).unwrap();
+        assert!(s.eq(SMALL_STR));
+        assert!(r.crc_32() == Some(crc32::run(0, SMALL_STR.as_bytes())));
+        assert!(r.uncompressed_size() == Some(SMALL_STR.len() as u64));
+
+        let mut r = Reader::new_streaming(PYTHON_ZIP.as_slice()).unwrap();
+        let mut s = String::new();
+        r.read_to_string(&mut s).unwrap();
+        assert!(s.eq("hello hello hello world\n"));
+    }
+
+    #[test]
+    fn corrupted_read() {
+        let mut archive = PYTHON_ZIP;
+        archive[14] ^= 1; // crc 32 in local file header
+        archive[91] ^= 1; // crc 32 in central directory header
+        let mut s = String::new();
+        let err = Reader::new(io::Cursor::new(archive)).unwrap().read_to_string(&mut s).unwrap_err();
+        assert!(err.kind() == ErrorKind::InvalidData);
+        assert!(matches!(ZipError::from(err), ZipError::ChecksumMismatch { .. }));
+        let err = Reader::new_streaming(archive.as_slice()).unwrap().read_to_string(&mut s).unwrap_err();
+        assert!(matches!(ZipError::from(err), ZipError::ChecksumMismatch { .. }));
+
+        let mut r = Reader::new(io::Cursor::new(archive)).unwrap();
+        r.set_verify(false);
+        let mut s = String::new();
+        r.read_to_string(&mut s).unwrap();
+        assert!(s.eq("hello hello hello world\n"));
+    }
+
+    #[test]
+    fn typed_errors() {
+        let long_name = "a".repeat(u16::MAX as usize + 1);
+        let err = Writer::new(io::Cursor::new(Vec::new()), long_name).err().unwrap();
+        assert!(matches!(err, ZipError::NameTooLong { length: 65536 }));
+        assert!(io::Error::from(err).kind() == ErrorKind::InvalidFilename);
+        let long_comment = "a".repeat(u16::MAX as usize + 1);
+        let err = WriterOptions::new().entry_name("a").archive_comment(long_comment).build_streaming(Vec::new()).err().unwrap();
+        assert!(matches!(err, ZipError::CommentTooLong { length: 65536 }));
+        // a comment looking like end of central directory record would be taken for it
+        let fake_record = "PK\x05\x06".to_string() + &"\0".repeat(18);
+        let err = WriterOptions::new().entry_name("a").archive_comment(fake_record).build_streaming(Vec::new()).err().unwrap();
+        assert!(matches!(err, ZipError::CommentContainsSignature) && err.kind() == ErrorKind::InvalidInput);
+
+        let err = Reader::new(io::Cursor::new(&PYTHON_ZIP[..100])).err().unwrap();
+        assert!(matches!(err, ZipError::EndOfCentralDirectoryNotFound));
+        let mut archive = PYTHON_ZIP;
+        archive[75] = 0; // signature of central directory header
+        let err = Reader::new(io::Cursor::new(archive)).err().unwrap();
+        assert!(matches!(err, ZipError::InvalidSignature { offset: 75, .. }));
+        let err = Reader::new_streaming(&PYTHON_ZIP[..20]).err().unwrap();
+        assert!(matches!(err, ZipError::Truncated { offset: 0 }));
+    }
+
+    #[test]
+    fn header_parsers() {
+        let eocd_pos = PYTHON_ZIP.len() as u64 - 22 - 14;
+        let eocd = EndOfCentralDirectoryRecord::read_from(&mut &PYTHON_ZIP[eocd_pos as usize..], eocd_pos).unwrap();
+        assert!(eocd.zip_file_comment_length == 14);
+        // python only writes zip64 end of central directory when needed
+        let err = Zip64EndOfCentralDirectoryLocator::read_from(&mut &PYTHON_ZIP[eocd_pos as usize - 20..], eocd_pos - 20).err().unwrap();
+        assert!(matches!(err, ZipError::InvalidSignature { offset: 110, .. }));
+        let cd_pos = eocd.offset_of_start_of_central_directory_with_respect_to_the_starting_disk_number as u64;
+        let cd_header = CentralDirectoryHeader::read_from(&mut &PYTHON_ZIP[cd_pos as usize..], cd_pos).unwrap();
+        let local_header = LocalFileHeader::read_from(&mut &PYTHON_ZIP[..], 0).unwrap();
+        assert!(cd_header.crc_32 == local_header.crc_32);
+
+        let err = CentralDirectoryHeader::read_from(&mut &PYTHON_ZIP[..], 0).err().unwrap();
+        assert!(matches!(err, ZipError::InvalidSignature { offset: 0, found: LocalFileHeader::SIGNATURE, .. }));
+        let err = EndOfCentralDirectoryRecord::read_from(&mut &PYTHON_ZIP[150..], 150).err().unwrap();
+        assert!(matches!(err, ZipError::Truncated { offset: 150 }));
+
+        let record = Zip64EndOfCentralDirectoryRecord {
+            signature: Zip64EndOfCentralDirectoryRecord::SIGNATURE,
+            size_of_zip64_end_of_central_directory_record: 44,
+            version_made_by: TARGET_ZIP_VERSION,
+            version_needed_to_extract: TARGET_ZIP_VERSION,
+            number_of_this_disk: 0,
+            number_of_the_disk_with_the_start_of_the_central_directory: 0,
+            total_number_of_entries_in_the_central_directory_on_this_disk: 1,
+            total_number_of_entries_in_the_central_directory: 1,
+            size_of_the_central_directory: 46,
+            offset_of_start_of_central_directory_with_respect_to_the_starting_disk_number: 1 << 32,
+        }.to_bytes();
+        assert!(Zip64EndOfCentralDirectoryRecord::read_from(&mut &record[..], 0).unwrap().to_bytes() == record);
+
+        let data_descriptor = DataDescriptor {
+            signature: DataDescriptor::SIGNATURE,
+            crc_32: 0x12345678,
+            compressed_size: 1 << 40,
+            uncompressed_size: 1 << 41,
+        }.to_bytes();
+        let parsed = DataDescriptor::read_from(&mut &data_descriptor[..], 0, true).unwrap();
+        assert!(parsed.to_bytes() == data_descriptor);
+        let parsed = DataDescriptor::read_from(&mut &data_descriptor[4..], 0, false).unwrap();
+        assert!(parsed.signature == [0; 4] && parsed.crc_32 == 0x12345678 && parsed.compressed_size == 0);
+    }
+
+    #[test]
+    fn validate_archives() {
+        let options = WriterOptions::new().entry_name("small.txt").comment("job 42");
+        let mut w = options.clone().build(io::Cursor::new(Vec::new())).unwrap();
+        w.write_all(SMALL_STR.as_bytes()).unwrap();
+        let mut bytes = w.finish().unwrap().into_inner();
+        assert!(validate(io::Cursor::new(&bytes)).unwrap().is_empty());
+        let mut w = options.build_streaming(Vec::new()).unwrap();
+        w.write_all(SMALL_STR.as_bytes()).unwrap();
+        assert!(validate(io::Cursor::new(w.finish().unwrap())).unwrap().is_empty());
+        assert!(validate(io::Cursor::new(PYTHON_ZIP)).unwrap().is_empty());
+
+        // file comment length where file name length belongs in central directory header
+        let cd_pos = bytes.windows(4).position(|s| s == CentralDirectoryHeader::SIGNATURE).unwrap();
+        bytes.copy_within(cd_pos + 32..cd_pos + 34, cd_pos + 28);
+        let findings = validate(io::Cursor::new(&bytes)).unwrap();
+        assert!(findings.contains(&Finding::Mismatch { offset: 0, field: "file_name_length", expected: 6, found: 9 }));
+
+        let mut archive = PYTHON_ZIP;
+        archive[14] ^= 1; // crc 32 in local file header
+        let findings = validate(io::Cursor::new(archive)).unwrap();
+        assert!(matches!(findings.as_slice(), [Finding::Mismatch { offset: 0, field: "crc_32", .. }]));
+        let mut archive = PYTHON_ZIP.to_vec();
+        archive.extend_from_slice(b"junk");
+        let findings = validate(io::Cursor::new(archive)).unwrap();
+        assert!(findings == [Finding::TrailingData { offset: 166, length: 4 }]);
+    }
+
+    #[test]
+    fn crc32_kernels() {
+        let mut rng = PcgXshRr::new(2026);
+        let data = (0..8192).map(|_| rng.get() as u8).collect::<Vec<_>>();
+        assert!(crc32::run(0, b"123456789") == 0xcbf43926);
+        for _ in 0..500 {
+            let start = rng.get() as usize % 64;
+            let len = rng.get() as usize % (data.len() - start);
+            let bytes = &data[start..start + len];
+            let crc = rng.get();
+            let expected = crc32::run_bytewise(crc, bytes);
+            assert!(crc32::run_slice_by_16(crc, bytes) == expected);
+            assert!(crc32::run(crc, bytes) == expected);
+        }
+    }
+
+    #[test]
+    fn crc32_combine() {
+        let mut rng = PcgXshRr::new(42);
+        let data = (0..4096).map(|_| rng.get() as u8).collect::<Vec<_>>();
+        for _ in 0..100 {
+            let len = rng.get() as usize % data.len();
+            let split = rng.get() as usize % (len + 1);
+            let (a, b) = data[..len].split_at(split);
+            let combined = Crc32::combine(Crc32::checksum(a), Crc32::checksum(b), b.len() as u64);
+            assert!(combined == Crc32::checksum(&data[..len]));
+        }
+
+        let mut hasher = Crc32::new();
+        std::hash::Hasher::write(&mut hasher, SMALL_STR.as_bytes());
+        assert!(std::hash::Hasher::finish(&hasher) == crc32::run(0, SMALL_STR.as_bytes()) as u64);
+        let mut crc = Crc32::from_crc(Crc32::checksum(&SMALL_STR.as_bytes()[..10]));
+        crc.update(&SMALL_STR.as_bytes()[10..]);
+        assert!(crc.finalize() == hasher.finalize());
+    }
+
     #[test]
     fn zero_read() {
         zero_write();
         let mut r = Reader::open(Path::new(ZERO_ZIP)).unwrap();
         let mut buf = Vec::new();
         r.read_to_end(&mut buf).unwrap();
-        assert!(buf.len() == 0);
+        assert!(buf.is_empty());
     }
 
     #[test]
@@ -325,9 +2094,3 @@ mod tests {
         assert!(s.eq(SMALL_STR));
     }
 }
-
-fn read_u16(r: &mut impl io::Read) -> io::Result<u16> {
-    let mut buf = [0u8; 2];
-    r.read_exact(&mut buf)?;
-    Ok(u16::from_le_bytes(buf))
-}
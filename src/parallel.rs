@@ -0,0 +1,181 @@
+use std::{
+    collections::VecDeque,
+    io::{
+        self,
+        Write,
+    },
+    sync::{
+        mpsc,
+        Arc,
+        Mutex,
+    },
+    thread,
+};
+use flate2::{
+    Compress,
+    Compression,
+    FlushCompress,
+};
+
+use crate::{
+    CountingWriter,
+    crc32::{
+        self,
+        Crc32,
+    },
+};
+
+
+/// Input size of each block compressed by a worker.
+const BLOCK_SIZE: usize = 128 * 1024;
+/// Deflate looks back at most 32 KiB, that much of the previous block primes the next one.
+const WINDOW_SIZE: usize = 32 * 1024;
+
+/// Deflate on a pool of worker threads, like pigz.
+///
+/// Every block is compressed on its own after priming the compressor with the tail of the previous block,
+/// and ends with a sync flush so blocks are byte aligned and can be simply concatenated.
+/// The stream is closed by an empty final block.
+pub(crate) struct ParallelDeflate<W: Write> {
+    sink: CountingWriter<W>,
+    compression: Compression,
+    block: Vec<u8>,
+    /// Tail of the last submitted block
+    dictionary: Vec<u8>,
+    jobs: mpsc::Sender<Job>,
+    /// Compressed blocks in the order of submission
+    pending: VecDeque<mpsc::Receiver<CompressedBlock>>,
+    max_pending: usize,
+    crc_32: u32,
+}
+
+struct Job {
+    dictionary: Vec<u8>,
+    data: Vec<u8>,
+    compression: Compression,
+    result: mpsc::Sender<CompressedBlock>,
+}
+
+struct CompressedBlock {
+    data: Vec<u8>,
+    crc_32: u32,
+    len: u64,
+}
+
+impl<W: Write> ParallelDeflate<W> {
+    pub fn new(sink: CountingWriter<W>, compression: Compression, threads: usize) -> Self {
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..threads {
+            let receiver = Arc::clone(&receiver);
+            // workers stop when `jobs` is dropped
+            thread::spawn(move || loop {
+                let job = receiver.lock().unwrap().recv();
+                let Ok(job) = job else { break };
+                let _ = job.result.send(CompressedBlock {
+                    data: compress_block(&job.dictionary, &job.data, job.compression),
+                    crc_32: crc32::run(0, &job.data),
+                    len: job.data.len() as u64,
+                });
+            });
+        }
+        Self {
+            sink,
+            compression,
+            block: Vec::with_capacity(BLOCK_SIZE),
+            dictionary: Vec::new(),
+            jobs,
+            pending: VecDeque::new(),
+            max_pending: threads * 2,
+            crc_32: 0,
+        }
+    }
+
+    /// Crc 32 of everything written so far, only complete after `finish`.
+    pub fn finish(mut self) -> io::Result<(CountingWriter<W>, u32)> {
+        self.submit()?;
+        while !self.pending.is_empty() {
+            self.write_next()?;
+        }
+        // final block with fixed huffman codes and nothing but end of block
+        self.sink.write_all(&[0x03, 0x00])?;
+        Ok((self.sink, self.crc_32))
+    }
+
+    fn submit(&mut self) -> io::Result<()> {
+        if self.block.is_empty() {
+            return Ok(());
+        }
+        while self.pending.len() >= self.max_pending {
+            self.write_next()?;
+        }
+        let data = std::mem::replace(&mut self.block, Vec::with_capacity(BLOCK_SIZE));
+        let dictionary = std::mem::replace(&mut self.dictionary, data[data.len().saturating_sub(WINDOW_SIZE)..].to_vec());
+        let (result, receiver) = mpsc::channel();
+        self.jobs.send(Job { dictionary, data, compression: self.compression, result })
+            .map_err(|_| worker_error())?;
+        self.pending.push_back(receiver);
+        Ok(())
+    }
+
+    fn write_next(&mut self) -> io::Result<()> {
+        let Some(receiver) = self.pending.pop_front() else { return Ok(()) };
+        let block = receiver.recv().map_err(|_| worker_error())?;
+        self.sink.write_all(&block.data)?;
+        self.crc_32 = Crc32::combine(self.crc_32, block.crc_32, block.len);
+        Ok(())
+    }
+}
+
+impl<W: Write> io::Write for ParallelDeflate<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(BLOCK_SIZE - self.block.len());
+        self.block.extend_from_slice(&buf[..len]);
+        if self.block.len() == BLOCK_SIZE {
+            self.submit()?;
+        }
+        Ok(len)
+    }
+
+    /// Compress what is buffered as a shorter block and wait for all blocks.
+    fn flush(&mut self) -> io::Result<()> {
+        self.submit()?;
+        while !self.pending.is_empty() {
+            self.write_next()?;
+        }
+        self.sink.flush()
+    }
+}
+
+fn worker_error() -> io::Error {
+    io::Error::other("compression worker panicked")
+}
+
+/// Raw deflate of `data` ending with a sync flush, back references may reach into `dictionary`.
+fn compress_block(dictionary: &[u8], data: &[u8], compression: Compression) -> Vec<u8> {
+    let mut compress = Compress::new(compression, false);
+    let mut output = Vec::with_capacity(data.len() + data.len() / 16 + 64);
+    if !dictionary.is_empty() {
+        // only fills the window, its output belongs to the previous block,
+        // miniz_oxide has no `Compress::set_dictionary` to do it without compressing
+        compress_all(&mut compress, dictionary, &mut output);
+        output.clear();
+    }
+    compress_all(&mut compress, data, &mut output);
+    output
+}
+
+fn compress_all(compress: &mut Compress, mut input: &[u8], output: &mut Vec<u8>) {
+    loop {
+        if output.len() == output.capacity() {
+            output.reserve(64 * 1024);
+        }
+        let total_in = compress.total_in();
+        compress.compress_vec(input, output, FlushCompress::Sync).expect("deflate does not fail on valid input");
+        input = &input[(compress.total_in() - total_in) as usize..];
+        // the flush is done once there is room left in the output
+        if input.is_empty() && output.len() < output.capacity() {
+            return;
+        }
+    }
+}
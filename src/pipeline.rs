@@ -0,0 +1,127 @@
+use std::{
+    io::{
+        self,
+        Write,
+    },
+    sync::mpsc,
+    thread::{
+        self,
+        JoinHandle,
+    },
+};
+
+use crate::{
+    Writer,
+    ZipError,
+    poisoned_error,
+};
+
+
+/// Bytes collected before they are handed to the writer thread.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+enum Message {
+    Data(Vec<u8>),
+    /// The result of flushing is sent back
+    Flush(mpsc::SyncSender<io::Result<()>>),
+    Finish,
+}
+
+/// `Writer` running on a background thread, see `Writer::pipelined`.
+///
+/// Writes are collected into 64 KiB buffers and queued to the writer thread,
+/// which computes the crc 32, compresses and writes into the sink while the caller produces the next buffers.
+/// An error on the writer thread is returned by the next `write`, `flush` or by `finish`.
+pub struct PipelinedWriter<W: Write + Send + 'static> {
+    buffer: Vec<u8>,
+    /// `None` after the writer thread has stopped
+    sender: Option<mpsc::SyncSender<Message>>,
+    worker: Option<JoinHandle<Result<W, ZipError>>>,
+}
+
+impl<W: Write + Send + 'static> Writer<W> {
+    /// Move the writer onto a background thread, at most `queue_length` buffers wait for it.
+    ///
+    /// Dropping the returned writer without `finish` leaves the archive unfinished, like dropping `Writer`.
+    pub fn pipelined(mut self, queue_length: usize) -> PipelinedWriter<W> {
+        let (sender, receiver) = mpsc::sync_channel(queue_length.max(1));
+        let worker = thread::spawn(move || {
+            for message in receiver {
+                match message {
+                    Message::Data(buffer) => self.write_all(&buffer)?,
+                    Message::Flush(done) => {
+                        let _ = done.send(self.flush());
+                    },
+                    Message::Finish => return self.finish(),
+                }
+            }
+            Err(poisoned_error().into())
+        });
+        PipelinedWriter {
+            buffer: Vec::with_capacity(BUFFER_SIZE),
+            sender: Some(sender),
+            worker: Some(worker),
+        }
+    }
+}
+
+impl<W: Write + Send + 'static> PipelinedWriter<W> {
+    pub fn finish(mut self) -> Result<W, ZipError> {
+        self.send_buffer()?;
+        self.send(Message::Finish)?;
+        self.sender = None;
+        self.join()
+    }
+
+    fn send_buffer(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let buffer = std::mem::replace(&mut self.buffer, Vec::with_capacity(BUFFER_SIZE));
+        self.send(Message::Data(buffer))
+    }
+
+    fn send(&mut self, message: Message) -> io::Result<()> {
+        let Some(sender) = &self.sender else {
+            return Err(poisoned_error());
+        };
+        if sender.send(message).is_err() {
+            return Err(self.worker_error());
+        }
+        Ok(())
+    }
+
+    /// The writer thread only hangs up after an error, take it.
+    fn worker_error(&mut self) -> io::Error {
+        self.sender = None;
+        self.join().err().unwrap_or_else(|| poisoned_error().into()).into()
+    }
+
+    /// Wait for the writer thread, its error is only returned once.
+    fn join(&mut self) -> Result<W, ZipError> {
+        let worker = self.worker.take().ok_or_else(poisoned_error)?;
+        worker.join().unwrap_or_else(|_| Err(io::Error::other("writer thread panicked").into()))
+    }
+}
+
+impl<W: Write + Send + 'static> io::Write for PipelinedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.sender.is_none() {
+            return Err(poisoned_error());
+        }
+        let len = buf.len().min(BUFFER_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..len]);
+        if self.buffer.len() == BUFFER_SIZE {
+            self.send_buffer()?;
+        }
+        Ok(len)
+    }
+
+    /// Wait until the writer thread has written and flushed everything before.
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_buffer()?;
+        let (done, result) = mpsc::sync_channel(1);
+        self.send(Message::Flush(done))?;
+        result.recv().unwrap_or_else(|_| Err(self.worker_error()))
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    io::{
+        self,
+        Read,
+    },
+    sync::mpsc,
+    thread::{
+        self,
+        JoinHandle,
+    },
+};
+
+use crate::{
+    CompressionMethod,
+    Decoder,
+    Reader,
+};
+
+
+/// Size of each buffer in the ring.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Decoder running on a background thread, see `Reader::readahead`.
+///
+/// Buffers circulate between the two threads: the background thread fills free buffers
+/// with decompressed data, and they are given back once they are read out.
+/// The decoder comes back to the reading thread at the end of the entry or on error.
+pub(crate) struct Readahead<R: Read> {
+    compression_method: CompressionMethod,
+    /// Buffer being read out
+    current: Vec<u8>,
+    pos: usize,
+    /// Buffers given back to the background thread, `None` after it is joined
+    free: Option<mpsc::SyncSender<Vec<u8>>>,
+    /// Buffers filled by the background thread, or its error
+    filled: Option<mpsc::Receiver<io::Result<Vec<u8>>>>,
+    worker: Option<JoinHandle<Decoder<R>>>,
+    /// The decoder after the background thread stopped
+    decoder: Option<Decoder<R>>,
+}
+
+impl<R: Read + Send + 'static> Reader<R> {
+    /// Decompress ahead on a background thread into a ring of `buffers` buffers of 64 KiB,
+    /// reading from the returned reader is otherwise unchanged.
+    ///
+    /// Call it before reading anything, the metadata of `Reader` is still read on the calling thread.
+    pub fn readahead(mut self, buffers: usize) -> Self {
+        if !matches!(self.decoder, Decoder::Readahead(_)) {
+            self.decoder = Decoder::Readahead(Box::new(Readahead::new(self.decoder, buffers.max(1))));
+        }
+        self
+    }
+}
+
+impl<R: Read + Send + 'static> Readahead<R> {
+    pub fn new(mut decoder: Decoder<R>, buffers: usize) -> Self {
+        let compression_method = decoder.compression_method();
+        let (free, free_receiver) = mpsc::sync_channel::<Vec<u8>>(buffers);
+        let (filled_sender, filled) = mpsc::sync_channel(buffers);
+        for _ in 0..buffers {
+            free.send(Vec::with_capacity(BUFFER_SIZE)).unwrap();
+        }
+        // stops at the end of the entry, on error, or when the reading side hangs up
+        let worker = thread::spawn(move || {
+            while let Ok(mut buffer) = free_receiver.recv() {
+                let result = fill(&mut decoder, &mut buffer);
+                // what is decompressed before an error is still delivered, an empty buffer marks the end
+                if !buffer.is_empty() || result.is_ok() {
+                    let end = buffer.is_empty();
+                    if filled_sender.send(Ok(buffer)).is_err() || end {
+                        break;
+                    }
+                }
+                if let Err(err) = result {
+                    let _ = filled_sender.send(Err(err));
+                    break;
+                }
+            }
+            decoder
+        });
+        Self {
+            compression_method,
+            current: Vec::new(),
+            pos: 0,
+            free: Some(free),
+            filled: Some(filled),
+            worker: Some(worker),
+            decoder: None,
+        }
+    }
+}
+
+impl<R: Read> Readahead<R> {
+    pub fn compression_method(&self) -> CompressionMethod {
+        self.compression_method
+    }
+
+    /// The decoder is only back after the end of the entry has been read.
+    pub fn decoder(&self) -> &Decoder<R> {
+        self.decoder.as_ref().expect("readahead has not reached the end of the entry")
+    }
+
+    pub fn decoder_mut(&mut self) -> &mut Decoder<R> {
+        self.decoder.as_mut().expect("readahead has not reached the end of the entry")
+    }
+
+    /// Stop the background thread and take the decoder back.
+    pub fn into_inner(mut self) -> Decoder<R> {
+        self.join();
+        self.decoder.unwrap()
+    }
+
+    fn join(&mut self) {
+        // the background thread stops once both channels hang up
+        self.free = None;
+        self.filled = None;
+        if let Some(worker) = self.worker.take() {
+            self.decoder = Some(worker.join().unwrap_or_else(|payload| std::panic::resume_unwind(payload)));
+        }
+    }
+}
+
+impl<R: Read> io::Read for Readahead<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.current.len() {
+            if let Some(decoder) = &mut self.decoder {
+                return decoder.read(buf);
+            }
+            if self.current.capacity() != 0 {
+                // can not block, there are never more buffers than the capacity of the channel
+                let _ = self.free.as_ref().unwrap().send(std::mem::take(&mut self.current));
+            }
+            self.pos = 0;
+            match self.filled.as_ref().unwrap().recv() {
+                Ok(Ok(buffer)) if !buffer.is_empty() => self.current = buffer,
+                Ok(Ok(_)) => self.join(),
+                Ok(Err(err)) => {
+                    self.join();
+                    return Err(err);
+                },
+                // the background thread panicked, it is resumed here
+                Err(_) => self.join(),
+            }
+        }
+        let len = buf.len().min(self.current.len() - self.pos);
+        buf[..len].copy_from_slice(&self.current[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+/// Read from `decoder` until `buffer` is full or the end of the entry is reached.
+fn fill(decoder: &mut impl Read, buffer: &mut Vec<u8>) -> io::Result<()> {
+    buffer.resize(BUFFER_SIZE, 0);
+    let mut len = 0;
+    let result = loop {
+        if len == buffer.len() {
+            break Ok(());
+        }
+        match decoder.read(&mut buffer[len..]) {
+            Ok(0) => break Ok(()),
+            Ok(n) => len += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {},
+            Err(err) => break Err(err),
+        }
+    };
+    buffer.truncate(len);
+    result
+}
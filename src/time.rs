@@ -0,0 +1,167 @@
+use std::time::{
+    Duration,
+    SystemTime,
+    UNIX_EPOCH,
+};
+
+
+/// Date and time in MS-DOS format as stored in zip headers, with 2 seconds precision.
+///
+/// Zip does not say which time zone it is in, most tools (Python, 7-Zip, Windows) use local time.
+/// Conversions from and to `SystemTime` here take it as UTC, so for such archives they are off by
+/// the UTC offset of the writer. `Writer` also writes the exact time in extended timestamp extra field,
+/// and `Reader::modified` only trusts extra fields.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DosDateTime {
+    pub time: u16,
+    pub date: u16,
+}
+impl DosDateTime {
+    /// 1980-01-01 00:00:00, the earliest representable time.
+    pub const MIN: Self = Self { time: 0, date: (1 << 5) | 1 };
+    /// 2107-12-31 23:59:58, the latest representable time.
+    pub const MAX: Self = Self { time: (23 << 11) | (59 << 5) | 29, date: (127 << 9) | (12 << 5) | 31 };
+
+    /// `None` if out of the DOS range or not a valid date, odd seconds are rounded down.
+    pub fn new(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Option<Self> {
+        if !(1980..=2107).contains(&year) || !(1..=12).contains(&month) || day < 1 || day > days_in_month(year as i64, month as u32) as u8
+            || hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+        Some(Self {
+            time: ((hour as u16) << 11) | ((minute as u16) << 5) | (second as u16 / 2),
+            date: ((year - 1980) << 9) | ((month as u16) << 5) | day as u16,
+        })
+    }
+
+    pub fn year(&self) -> u16 { (self.date >> 9) + 1980 }
+    pub fn month(&self) -> u8 { ((self.date >> 5) & 0xF) as u8 }
+    pub fn day(&self) -> u8 { (self.date & 0x1F) as u8 }
+    pub fn hour(&self) -> u8 { (self.time >> 11) as u8 }
+    pub fn minute(&self) -> u8 { ((self.time >> 5) & 0x3F) as u8 }
+    pub fn second(&self) -> u8 { ((self.time & 0x1F) * 2) as u8 }
+
+    /// Time out of the DOS range is clamped to `MIN` or `MAX`.
+    pub fn from_system_time(t: SystemTime) -> Self {
+        let secs = match t.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs() as i64,
+            Err(_) => return Self::MIN,
+        };
+        let (year, month, day) = civil_from_days(secs.div_euclid(86400));
+        let secs_of_day = secs.rem_euclid(86400);
+        if year < 1980 {
+            return Self::MIN;
+        }
+        if year > 2107 {
+            return Self::MAX;
+        }
+        Self::new(
+            year as u16, month as u8, day as u8,
+            (secs_of_day / 3600) as u8, (secs_of_day % 3600 / 60) as u8, (secs_of_day % 60) as u8,
+        ).unwrap()
+    }
+
+    /// `None` if the fields do not form a valid date, e.g. 0 which is written by many tools as "unknown".
+    pub fn to_system_time(&self) -> Option<SystemTime> {
+        let checked = Self::new(self.year(), self.month(), self.day(), self.hour(), self.minute(), self.second())?;
+        let days = days_from_civil(checked.year() as i64, checked.month() as u32, checked.day() as u32);
+        let secs = days * 86400 + checked.hour() as i64 * 3600 + checked.minute() as i64 * 60 + checked.second() as i64;
+        Some(UNIX_EPOCH + Duration::from_secs(secs as u64))
+    }
+}
+
+impl From<SystemTime> for DosDateTime {
+    fn from(t: SystemTime) -> Self {
+        Self::from_system_time(t)
+    }
+}
+
+/// Timestamps kept in the NTFS extra field, with 100 nanoseconds precision.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NtfsTimes {
+    pub modified: SystemTime,
+    pub accessed: SystemTime,
+    pub created: SystemTime,
+}
+
+/// Signed seconds since unix epoch as stored in the extended timestamp extra field, `None` if out of `i32`,
+/// times before the epoch are rounded down to whole seconds.
+pub fn to_unix_time(t: SystemTime) -> Option<i32> {
+    let secs = match t.duration_since(UNIX_EPOCH) {
+        Ok(d) => i64::try_from(d.as_secs()).ok()?,
+        Err(e) => {
+            let d = e.duration();
+            -i64::try_from(d.as_secs()).ok()? - (d.subsec_nanos() != 0) as i64
+        },
+    };
+    secs.try_into().ok()
+}
+
+pub fn from_unix_time(secs: i32) -> SystemTime {
+    if secs >= 0 {
+        UNIX_EPOCH + Duration::from_secs(secs as u64)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs() as u64)
+    }
+}
+
+/// Seconds between 1601-01-01 and 1970-01-01
+const FILETIME_UNIX_EPOCH: u64 = 11644473600;
+
+/// 100 nanoseconds since 1601-01-01 as stored in the NTFS extra field, clamped to `0` and `u64::MAX`.
+pub fn to_filetime(t: SystemTime) -> u64 {
+    let (after_epoch, d) = match t.duration_since(UNIX_EPOCH) {
+        Ok(d) => (true, d),
+        Err(e) => (false, e.duration()),
+    };
+    let ticks = d.as_nanos() / 100;
+    let epoch = FILETIME_UNIX_EPOCH as u128 * 10_000_000;
+    let ticks = if after_epoch { epoch + ticks } else { epoch.saturating_sub(ticks) };
+    ticks.min(u64::MAX as u128) as u64
+}
+
+pub fn from_filetime(ticks: u64) -> SystemTime {
+    let d = Duration::new(ticks / 10_000_000, (ticks % 10_000_000) as u32 * 100);
+    let epoch = Duration::from_secs(FILETIME_UNIX_EPOCH);
+    if d >= epoch {
+        UNIX_EPOCH + (d - epoch)
+    } else {
+        UNIX_EPOCH - (epoch - d)
+    }
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// `(year, month, day)` of days since 1970-01-01,
+/// see <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Days since 1970-01-01 of `(year, month, day)`,
+/// see <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
@@ -0,0 +1,358 @@
+use std::{
+    fmt,
+    io::{
+        Read,
+        Seek,
+        SeekFrom,
+    },
+};
+
+use crate::{
+    find_end_of_central_directory,
+    read_array_at,
+    common::{
+        CentralDirectoryHeader,
+        DataDescriptor,
+        LocalFileHeader,
+        Zip64EndOfCentralDirectoryLocator,
+        Zip64EndOfCentralDirectoryRecord,
+        Zip64ExtraField,
+        Zip64ExtraFieldSelect,
+        find_extra_field,
+        GENERAL_PURPOSE_BIT_FLAG_DATA_DESCRIPTOR,
+    },
+    error::ZipError,
+};
+
+
+/// A discrepancy found by `validate`, `offset` is where the offending header starts.
+///
+/// For values that appear more than once, the central directory (or zip64 end of central directory record)
+/// is taken as the reference: `expected` comes from it and `found` from the header at `offset`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Finding {
+    InvalidSignature { offset: u64, expected: [u8; 4], found: [u8; 4] },
+    /// The header at `offset` runs past where it has to end.
+    Truncated { offset: u64 },
+    Mismatch { offset: u64, field: &'static str, expected: u64, found: u64 },
+    /// Same lengths but different bytes of file name in local file header.
+    FileNameMismatch { offset: u64 },
+    /// `field` of the header at `offset` points outside of where it may point to.
+    OutOfRange { offset: u64, field: &'static str, value: u64 },
+    /// Some fields of the header at `offset` are saturated but the zip64 extra field is missing or too short.
+    MissingZip64ExtraField { offset: u64 },
+    /// Fields of end of central directory record are saturated but there is no zip64 end of central directory.
+    MissingZip64EndOfCentralDirectory { offset: u64 },
+    /// `length` bytes before the archive, offsets in headers are all off by it.
+    PrefixedData { length: u64 },
+    /// `length` bytes after the archive comment.
+    TrailingData { offset: u64, length: u64 },
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSignature { offset, expected, found } =>
+                write!(f, "invalid signature at offset {offset}: expected {expected:02x?}, found {found:02x?}"),
+            Self::Truncated { offset } => write!(f, "header at offset {offset} is truncated"),
+            Self::Mismatch { offset, field, expected, found } =>
+                write!(f, "{field} of header at offset {offset} mismatches: expected {expected}, found {found}"),
+            Self::FileNameMismatch { offset } => write!(f, "file name of local file header at offset {offset} mismatches"),
+            Self::OutOfRange { offset, field, value } =>
+                write!(f, "{field} of header at offset {offset} is out of range: {value}"),
+            Self::MissingZip64ExtraField { offset } => write!(f, "missing zip64 extra field in header at offset {offset}"),
+            Self::MissingZip64EndOfCentralDirectory { offset } =>
+                write!(f, "end of central directory record at offset {offset} is saturated without zip64 end of central directory"),
+            Self::PrefixedData { length } => write!(f, "{length} bytes before the archive"),
+            Self::TrailingData { offset, length } => write!(f, "{length} bytes after the archive at offset {offset}"),
+        }
+    }
+}
+
+
+/// Check an archive against APPNOTE by cross-checking local file headers, data descriptors,
+/// central directory headers, zip64 fields and end of central directory record.
+/// All entries are checked, not only singleton zips.
+///
+/// Compressed data is not decompressed, `Reader` verifies its crc and sizes.
+/// Errors are only returned if the archive cannot be located at all or on io failures.
+pub fn validate<R: Read + Seek>(mut source: R) -> Result<Vec<Finding>, ZipError> {
+    let mut findings = Vec::new();
+    let file_size = source.seek(SeekFrom::End(0))?;
+    let (eocd_pos, eocd, archive_comment) = find_end_of_central_directory(&mut source)?;
+    let archive_end = eocd_pos + 22 + archive_comment.len() as u64;
+    if archive_end != file_size {
+        findings.push(Finding::TrailingData { offset: archive_end, length: file_size - archive_end });
+    }
+    if eocd.total_number_of_entries_in_the_central_directory_on_this_disk != eocd.total_number_of_entries_in_the_central_directory {
+        findings.push(Finding::Mismatch {
+            offset: eocd_pos,
+            field: "total_number_of_entries_in_the_central_directory_on_this_disk",
+            expected: eocd.total_number_of_entries_in_the_central_directory as u64,
+            found: eocd.total_number_of_entries_in_the_central_directory_on_this_disk as u64,
+        });
+    }
+
+    let mut number_of_entries = eocd.total_number_of_entries_in_the_central_directory as u64;
+    let mut cd_size = eocd.size_of_the_central_directory as u64;
+    let mut cd_pos = eocd.offset_of_start_of_central_directory_with_respect_to_the_starting_disk_number as u64;
+    let mut cd_end_pos = eocd_pos;
+    let locator = if eocd_pos >= 20 {
+        Some(Zip64EndOfCentralDirectoryLocator::from_bytes(&read_array_at(&mut source, eocd_pos - 20)?))
+            .filter(|locator| locator.signature == Zip64EndOfCentralDirectoryLocator::SIGNATURE)
+    } else { None };
+    match locator {
+        Some(locator) => {
+            let locator_pos = eocd_pos - 20;
+            if locator.total_number_of_disks != 1 {
+                findings.push(Finding::Mismatch { offset: locator_pos, field: "total_number_of_disks", expected: 1, found: locator.total_number_of_disks as u64 });
+            }
+            let mut record_pos = locator.relative_offset_of_the_zip64_end_of_central_directory_record;
+            if record_pos.saturating_add(56) > locator_pos {
+                findings.push(Finding::OutOfRange {
+                    offset: locator_pos,
+                    field: "relative_offset_of_the_zip64_end_of_central_directory_record",
+                    value: record_pos,
+                });
+                return Ok(findings);
+            }
+            let mut record = Zip64EndOfCentralDirectoryRecord::from_bytes(&read_array_at(&mut source, record_pos)?);
+            if record.signature != Zip64EndOfCentralDirectoryRecord::SIGNATURE && locator_pos >= 56 {
+                // archive has been prefixed, reported below when the prefix is known
+                let prefixed_record = Zip64EndOfCentralDirectoryRecord::from_bytes(&read_array_at(&mut source, locator_pos - 56)?);
+                if prefixed_record.signature == Zip64EndOfCentralDirectoryRecord::SIGNATURE {
+                    record_pos = locator_pos - 56;
+                    record = prefixed_record;
+                }
+            }
+            if record.signature != Zip64EndOfCentralDirectoryRecord::SIGNATURE {
+                findings.push(Finding::InvalidSignature {
+                    offset: record_pos,
+                    expected: Zip64EndOfCentralDirectoryRecord::SIGNATURE,
+                    found: record.signature,
+                });
+                return Ok(findings);
+            }
+            // the record may have an extensible data sector, but nothing is between it and the locator
+            if record.size_of_zip64_end_of_central_directory_record != locator_pos - record_pos - 12 {
+                findings.push(Finding::Mismatch {
+                    offset: record_pos,
+                    field: "size_of_zip64_end_of_central_directory_record",
+                    expected: locator_pos - record_pos - 12,
+                    found: record.size_of_zip64_end_of_central_directory_record,
+                });
+            }
+            if record.total_number_of_entries_in_the_central_directory_on_this_disk != record.total_number_of_entries_in_the_central_directory {
+                findings.push(Finding::Mismatch {
+                    offset: record_pos,
+                    field: "total_number_of_entries_in_the_central_directory_on_this_disk",
+                    expected: record.total_number_of_entries_in_the_central_directory,
+                    found: record.total_number_of_entries_in_the_central_directory_on_this_disk,
+                });
+            }
+            // values in end of central directory record are either saturated or the same
+            let pairs = [
+                ("total_number_of_entries_in_the_central_directory", record.total_number_of_entries_in_the_central_directory,
+                    eocd.total_number_of_entries_in_the_central_directory as u64, u16::MAX as u64),
+                ("size_of_the_central_directory", record.size_of_the_central_directory,
+                    eocd.size_of_the_central_directory as u64, u32::MAX as u64),
+                ("offset_of_start_of_central_directory_with_respect_to_the_starting_disk_number",
+                    record.offset_of_start_of_central_directory_with_respect_to_the_starting_disk_number,
+                    eocd.offset_of_start_of_central_directory_with_respect_to_the_starting_disk_number as u64, u32::MAX as u64),
+            ];
+            for (field, expected, found, saturated) in pairs {
+                if found != saturated && found != expected {
+                    findings.push(Finding::Mismatch { offset: eocd_pos, field, expected, found });
+                }
+            }
+            number_of_entries = record.total_number_of_entries_in_the_central_directory;
+            cd_size = record.size_of_the_central_directory;
+            cd_pos = record.offset_of_start_of_central_directory_with_respect_to_the_starting_disk_number;
+            cd_end_pos = record_pos;
+        },
+        None => {
+            if eocd.total_number_of_entries_in_the_central_directory == u16::MAX
+                || eocd.size_of_the_central_directory == u32::MAX
+                || eocd.offset_of_start_of_central_directory_with_respect_to_the_starting_disk_number == u32::MAX {
+                findings.push(Finding::MissingZip64EndOfCentralDirectory { offset: eocd_pos });
+            }
+            if eocd.number_of_this_disk != 0 {
+                findings.push(Finding::Mismatch { offset: eocd_pos, field: "number_of_this_disk", expected: 0, found: eocd.number_of_this_disk as u64 });
+            }
+        },
+    }
+
+    let Some(archive_offset) = cd_end_pos.checked_sub(cd_pos.saturating_add(cd_size)) else {
+        findings.push(Finding::OutOfRange {
+            offset: eocd_pos,
+            field: "offset_of_start_of_central_directory_with_respect_to_the_starting_disk_number",
+            value: cd_pos,
+        });
+        return Ok(findings);
+    };
+    if archive_offset != 0 {
+        findings.push(Finding::PrefixedData { length: archive_offset });
+    }
+    let cd_pos = cd_pos + archive_offset;
+
+    // walk through central directory
+    let mut pos = cd_pos;
+    let mut count = 0;
+    while pos < cd_end_pos {
+        if pos + 46 > cd_end_pos {
+            findings.push(Finding::Truncated { offset: pos });
+            break;
+        }
+        let cd_header = CentralDirectoryHeader::from_bytes(&read_array_at(&mut source, pos)?);
+        if cd_header.signature != CentralDirectoryHeader::SIGNATURE {
+            findings.push(Finding::InvalidSignature { offset: pos, expected: CentralDirectoryHeader::SIGNATURE, found: cd_header.signature });
+            break;
+        }
+        let variable_length = cd_header.file_name_length as u64 + cd_header.extra_field_length as u64 + cd_header.file_comment_length as u64;
+        if pos + 46 + variable_length > cd_end_pos {
+            findings.push(Finding::Truncated { offset: pos });
+            break;
+        }
+        let mut file_name = vec![0u8; cd_header.file_name_length as usize];
+        source.read_exact(&mut file_name)?;
+        let mut extra_fields = vec![0u8; cd_header.extra_field_length as usize];
+        source.read_exact(&mut extra_fields)?;
+        validate_entry(&mut source, &mut findings, pos, &cd_header, &file_name, &extra_fields, archive_offset, cd_pos)?;
+        pos += 46 + variable_length;
+        count += 1;
+    }
+    if pos == cd_end_pos && pos - cd_pos != cd_size {
+        findings.push(Finding::Mismatch { offset: cd_end_pos, field: "size_of_the_central_directory", expected: pos - cd_pos, found: cd_size });
+    }
+    if count != number_of_entries {
+        findings.push(Finding::Mismatch {
+            offset: cd_end_pos,
+            field: "total_number_of_entries_in_the_central_directory",
+            expected: count,
+            found: number_of_entries,
+        });
+    }
+    Ok(findings)
+}
+
+/// Check the local file header and the data descriptor of an entry against its central directory header at `cd_header_pos`.
+#[allow(clippy::too_many_arguments)]
+fn validate_entry<R: Read + Seek>(
+    source: &mut R,
+    findings: &mut Vec<Finding>,
+    cd_header_pos: u64,
+    cd_header: &CentralDirectoryHeader,
+    file_name: &[u8],
+    extra_fields: &[u8],
+    archive_offset: u64,
+    cd_pos: u64,
+) -> Result<(), ZipError> {
+    // resolve zip64 values in central directory header
+    let mut uncompressed_size = cd_header.uncompressed_size as u64;
+    let mut compressed_size = cd_header.compressed_size as u64;
+    let mut local_header_pos = cd_header.relative_offset_of_local_header as u64;
+    let mut selected = Vec::new();
+    if cd_header.uncompressed_size == u32::MAX { selected.push(Zip64ExtraFieldSelect::UncompressedSize); }
+    if cd_header.compressed_size == u32::MAX { selected.push(Zip64ExtraFieldSelect::CompressedSize); }
+    if cd_header.relative_offset_of_local_header == u32::MAX { selected.push(Zip64ExtraFieldSelect::RelativeOffsetOfLocalHeader); }
+    if cd_header.disk_number_start == u16::MAX { selected.push(Zip64ExtraFieldSelect::DiskNumberStart); }
+    if !selected.is_empty() {
+        match find_extra_field(extra_fields, Zip64ExtraField::HEADER_ID).and_then(|bytes| Zip64ExtraField::select_from_bytes(bytes, &selected)) {
+            Some(zip64_extra_field) => {
+                if cd_header.uncompressed_size == u32::MAX { uncompressed_size = zip64_extra_field.uncompressed_size; }
+                if cd_header.compressed_size == u32::MAX { compressed_size = zip64_extra_field.compressed_size; }
+                if cd_header.relative_offset_of_local_header == u32::MAX { local_header_pos = zip64_extra_field.relative_offset_of_local_header; }
+            },
+            None => {
+                findings.push(Finding::MissingZip64ExtraField { offset: cd_header_pos });
+                return Ok(());
+            },
+        }
+    }
+    let local_header_pos = local_header_pos + archive_offset;
+    if local_header_pos.saturating_add(30) > cd_pos {
+        findings.push(Finding::OutOfRange { offset: cd_header_pos, field: "relative_offset_of_local_header", value: local_header_pos - archive_offset });
+        return Ok(());
+    }
+
+    let local_header = LocalFileHeader::from_bytes(&read_array_at(source, local_header_pos)?);
+    if local_header.signature != LocalFileHeader::SIGNATURE {
+        findings.push(Finding::InvalidSignature { offset: local_header_pos, expected: LocalFileHeader::SIGNATURE, found: local_header.signature });
+        return Ok(());
+    }
+    let data_pos = local_header_pos + 30 + local_header.file_name_length as u64 + local_header.extra_field_length as u64;
+    if data_pos > cd_pos {
+        findings.push(Finding::Truncated { offset: local_header_pos });
+        return Ok(());
+    }
+    let mut mismatch = |field, expected: u64, found: u64| if expected != found {
+        findings.push(Finding::Mismatch { offset: local_header_pos, field, expected, found });
+    };
+    mismatch("version_needed_to_extract", cd_header.version_needed_to_extract as u64, local_header.version_needed_to_extract as u64);
+    mismatch("general_purpose_bit_flag", cd_header.general_purpose_bit_flag as u64, local_header.general_purpose_bit_flag as u64);
+    mismatch("compression_method", cd_header.compression_method as u64, local_header.compression_method as u64);
+    mismatch("last_modified_file_time", cd_header.last_modified_file_time as u64, local_header.last_modified_file_time as u64);
+    mismatch("last_modified_file_date", cd_header.last_modified_file_date as u64, local_header.last_modified_file_date as u64);
+    mismatch("file_name_length", cd_header.file_name_length as u64, local_header.file_name_length as u64);
+
+    let mut local_file_name_and_extra_fields = vec![0u8; (data_pos - local_header_pos - 30) as usize];
+    source.read_exact(&mut local_file_name_and_extra_fields)?;
+    let (local_file_name, local_extra_fields) = local_file_name_and_extra_fields.split_at(local_header.file_name_length as usize);
+    if local_file_name.len() == file_name.len() && local_file_name != file_name {
+        findings.push(Finding::FileNameMismatch { offset: local_header_pos });
+    }
+
+    // zip64 extra field in local file header always contains both sizes
+    let local_zip64_extra_field = find_extra_field(local_extra_fields, Zip64ExtraField::HEADER_ID);
+    let mut local_uncompressed_size = local_header.uncompressed_size as u64;
+    let mut local_compressed_size = local_header.compressed_size as u64;
+    if local_header.uncompressed_size == u32::MAX || local_header.compressed_size == u32::MAX {
+        match local_zip64_extra_field.and_then(|bytes| Zip64ExtraField::select_from_bytes(bytes, &[
+            Zip64ExtraFieldSelect::UncompressedSize,
+            Zip64ExtraFieldSelect::CompressedSize,
+        ])) {
+            Some(zip64_extra_field) => {
+                local_uncompressed_size = zip64_extra_field.uncompressed_size;
+                local_compressed_size = zip64_extra_field.compressed_size;
+            },
+            None => findings.push(Finding::MissingZip64ExtraField { offset: local_header_pos }),
+        }
+    }
+
+    let data_end = data_pos.saturating_add(compressed_size);
+    if data_end > cd_pos {
+        findings.push(Finding::OutOfRange { offset: cd_header_pos, field: "compressed_size", value: compressed_size });
+        return Ok(());
+    }
+    if cd_header.general_purpose_bit_flag & GENERAL_PURPOSE_BIT_FLAG_DATA_DESCRIPTOR == 0 {
+        let mut mismatch = |field, expected: u64, found: u64| if expected != found {
+            findings.push(Finding::Mismatch { offset: local_header_pos, field, expected, found });
+        };
+        mismatch("crc_32", cd_header.crc_32 as u64, local_header.crc_32 as u64);
+        mismatch("compressed_size", compressed_size, local_compressed_size);
+        mismatch("uncompressed_size", uncompressed_size, local_uncompressed_size);
+    } else {
+        // values in local file header are meaningless, the data descriptor is right after the data
+        source.seek(SeekFrom::Start(data_end))?;
+        let data_descriptor = match DataDescriptor::read_from(source, data_end, local_zip64_extra_field.is_some()) {
+            Ok(data_descriptor) => data_descriptor,
+            Err(ZipError::Truncated { offset }) => {
+                findings.push(Finding::Truncated { offset });
+                return Ok(());
+            },
+            Err(err) => return Err(err),
+        };
+        let data_descriptor_end = source.stream_position()?;
+        if data_descriptor_end > cd_pos {
+            findings.push(Finding::Truncated { offset: data_end });
+        }
+        let mut mismatch = |field, expected: u64, found: u64| if expected != found {
+            findings.push(Finding::Mismatch { offset: data_end, field, expected, found });
+        };
+        mismatch("crc_32", cd_header.crc_32 as u64, data_descriptor.crc_32 as u64);
+        mismatch("compressed_size", compressed_size, data_descriptor.compressed_size);
+        mismatch("uncompressed_size", uncompressed_size, data_descriptor.uncompressed_size);
+    }
+    Ok(())
+}